@@ -0,0 +1,37 @@
+//! Least significant bit first reading, as used by the legacy methods.
+
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    /// Position in bits.
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// Read `count` bits (up to 32). Returns `None` once the data runs out.
+    pub fn read(&mut self, count: usize) -> Option<u32> {
+        if self.position + count > self.data.len() * 8 {
+            return None;
+        }
+
+        let mut value = 0;
+
+        for i in 0..count {
+            let position = self.position + i;
+            let bit = (self.data[position / 8] >> (position % 8)) & 1;
+
+            value |= (bit as u32) << i;
+        }
+
+        self.position += count;
+
+        Some(value)
+    }
+
+    pub fn read_bit(&mut self) -> Option<bool> {
+        self.read(1).map(|v| v == 1)
+    }
+}
@@ -0,0 +1,85 @@
+//! BZIP2
+
+use std::io;
+
+use bzip2_rs::decoder::{Decoder as InnerDecoder, ReadState, WriteState};
+
+use super::{Decoded, Decoder, OUTPUT_CHUNK_SIZE};
+
+/// An empty write tells the decoder there's no more data coming.
+/// It can take a couple of them for the decoder to notice the end of the stream.
+const MAX_EMPTY_WRITES: usize = 2;
+
+pub(crate) struct Bzip2Decoder {
+    inner: Box<InnerDecoder>,
+}
+
+impl Bzip2Decoder {
+    pub fn new() -> Self {
+        Self {
+            inner: Box::default(),
+        }
+    }
+}
+
+impl Decoder for Bzip2Decoder {
+    fn decode(&mut self, input: &[u8], eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        let start = output.len();
+        output.resize(start + OUTPUT_CHUNK_SIZE, 0);
+
+        let mut consumed = 0;
+        let mut empty_writes = 0;
+
+        loop {
+            let state = self
+                .inner
+                .read(&mut output[start..])
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            match state {
+                ReadState::Read(amount) => {
+                    output.truncate(start + amount);
+
+                    return Ok(Decoded {
+                        consumed,
+                        finished: false,
+                    });
+                }
+
+                ReadState::Eof => {
+                    output.truncate(start);
+
+                    return Ok(Decoded {
+                        consumed,
+                        finished: true,
+                    });
+                }
+
+                ReadState::NeedsWrite(_) => {
+                    let data = if consumed < input.len() {
+                        &input[consumed..]
+                    } else if eof && empty_writes < MAX_EMPTY_WRITES {
+                        empty_writes += 1;
+                        &[]
+                    } else {
+                        output.truncate(start);
+
+                        return Ok(Decoded {
+                            consumed,
+                            finished: false,
+                        });
+                    };
+
+                    let state = self
+                        .inner
+                        .write(data)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+                    if let WriteState::Written(amount) = state {
+                        consumed += amount;
+                    }
+                }
+            }
+        }
+    }
+}
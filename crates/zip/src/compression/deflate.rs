@@ -0,0 +1,97 @@
+//! Deflate (RFC 1951)
+
+use std::io;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use super::{Decoded, Decoder, Encoder, OUTPUT_CHUNK_SIZE};
+
+/// Used when no compression level is given.
+pub(crate) const DEFAULT_DEFLATE_LEVEL: u32 = 6;
+
+pub(crate) struct DeflateDecoder {
+    inner: Decompress,
+}
+
+impl DeflateDecoder {
+    pub fn new() -> Self {
+        Self {
+            // Zip stores raw deflate data. There's no zlib header.
+            inner: Decompress::new(false),
+        }
+    }
+}
+
+impl Decoder for DeflateDecoder {
+    fn decode(&mut self, input: &[u8], _eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        // decompress_vec only writes into the spare capacity.
+        output.reserve(OUTPUT_CHUNK_SIZE);
+
+        let total_in = self.inner.total_in();
+
+        let status = self
+            .inner
+            .decompress_vec(input, output, FlushDecompress::None)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(Decoded {
+            consumed: (self.inner.total_in() - total_in) as usize,
+            finished: status == Status::StreamEnd,
+        })
+    }
+}
+
+pub(crate) struct DeflateEncoder {
+    inner: Compress,
+}
+
+impl DeflateEncoder {
+    pub fn new(level: Option<u32>) -> Self {
+        let level = level.unwrap_or(DEFAULT_DEFLATE_LEVEL).min(9);
+
+        Self {
+            inner: Compress::new(Compression::new(level), false),
+        }
+    }
+
+    fn compress(
+        &mut self,
+        mut input: &[u8],
+        output: &mut Vec<u8>,
+        flush: FlushCompress,
+    ) -> io::Result<()> {
+        loop {
+            // compress_vec only writes into the spare capacity.
+            output.reserve(OUTPUT_CHUNK_SIZE);
+
+            let total_in = self.inner.total_in();
+
+            let status = self
+                .inner
+                .compress_vec(input, output, flush)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            input = &input[(self.inner.total_in() - total_in) as usize..];
+
+            let done = match flush {
+                FlushCompress::Finish => status == Status::StreamEnd,
+                // Keep going until the output stops filling the spare capacity.
+                _ => input.is_empty() && output.len() < output.capacity(),
+            };
+
+            if done {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Encoder for DeflateEncoder {
+    fn encode(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        self.compress(input, output, FlushCompress::None)
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        self.compress(&[], output, FlushCompress::Finish)
+    }
+}
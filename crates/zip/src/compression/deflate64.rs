@@ -0,0 +1,42 @@
+//! Deflate64
+//!
+//! Deflate with a 64K window, length code 285 taking 16 extra bits and distance codes 30 and 31.
+
+use std::io;
+
+use deflate64::InflaterManaged;
+
+use super::{invalid_data, Decoded, Decoder, OUTPUT_CHUNK_SIZE};
+
+pub(crate) struct Deflate64Decoder {
+    // Holds the window so it's fairly large.
+    inner: Box<InflaterManaged>,
+}
+
+impl Deflate64Decoder {
+    pub fn new() -> Self {
+        Self {
+            inner: Box::new(InflaterManaged::new()),
+        }
+    }
+}
+
+impl Decoder for Deflate64Decoder {
+    fn decode(&mut self, input: &[u8], _eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        let start = output.len();
+        output.resize(start + OUTPUT_CHUNK_SIZE, 0);
+
+        let result = self.inner.inflate(input, &mut output[start..]);
+
+        output.truncate(start + result.bytes_written);
+
+        if result.data_error {
+            return Err(invalid_data("invalid deflate64 data"));
+        }
+
+        Ok(Decoded {
+            consumed: result.bytes_consumed,
+            finished: self.inner.finished(),
+        })
+    }
+}
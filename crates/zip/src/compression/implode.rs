@@ -0,0 +1,168 @@
+//! Implode
+//!
+//! LZ77 with a 4K or 8K dictionary. Lengths and the high distance bits are Shannon-Fano coded, as are the literals
+//! when three trees are used. Which variant was used is in general purpose bits 1 and 2.
+
+use std::io;
+
+use super::{bits::BitReader, invalid_data, output_capacity};
+
+const MAX_CODE_LENGTH: usize = 16;
+
+/// Decodes the Shannon-Fano codes.
+///
+/// With their bits inverted they're the same as canonical Huffman codes.
+struct Tree {
+    /// Amount of codes of each length.
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    /// Symbols ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl Tree {
+    /// Read the run-length encoded code lengths for `symbol_count` symbols.
+    fn read(bits: &mut BitReader<'_>, symbol_count: usize) -> io::Result<Self> {
+        let eof = || invalid_data("implode tree ended early");
+
+        let byte_count = bits.read(8).ok_or_else(eof)? as usize + 1;
+
+        let mut lengths = Vec::with_capacity(symbol_count);
+
+        for _ in 0..byte_count {
+            let byte = bits.read(8).ok_or_else(eof)?;
+
+            let length = (byte & 0xF) as usize + 1;
+            let repeat = (byte >> 4) as usize + 1;
+
+            if lengths.len() + repeat > symbol_count {
+                return Err(invalid_data("implode tree has too many code lengths"));
+            }
+
+            lengths.resize(lengths.len() + repeat, length);
+        }
+
+        if lengths.len() != symbol_count {
+            return Err(invalid_data("implode tree has too few code lengths"));
+        }
+
+        let mut counts = [0; MAX_CODE_LENGTH + 1];
+
+        for &length in &lengths {
+            counts[length] += 1;
+        }
+
+        // The codes have to fill the tree exactly.
+        let mut available = 1i32;
+
+        for &count in &counts[1..] {
+            available = available * 2 - count as i32;
+
+            if available < 0 {
+                return Err(invalid_data("implode tree is over-subscribed"));
+            }
+        }
+
+        if available != 0 {
+            return Err(invalid_data("implode tree is incomplete"));
+        }
+
+        let mut symbols = Vec::with_capacity(symbol_count);
+
+        for length in 1..=MAX_CODE_LENGTH {
+            for (symbol, _) in lengths.iter().enumerate().filter(|v| *v.1 == length) {
+                symbols.push(symbol as u16);
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader<'_>) -> Option<u16> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+
+        for &count in &self.counts[1..] {
+            code |= !bits.read_bit()? as i32;
+
+            let count = count as i32;
+
+            if code - first < count {
+                return Some(self.symbols[(index + code - first) as usize]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        None
+    }
+}
+
+/// `large_dictionary` and `literal_tree` are from general purpose bits 1 and 2.
+pub(crate) fn explode(
+    input: &[u8],
+    large_dictionary: bool,
+    literal_tree: bool,
+    uncompressed_size: u64,
+) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(output_capacity(uncompressed_size));
+
+    let uncompressed_size = uncompressed_size as usize;
+
+    let mut bits = BitReader::new(input);
+
+    let literals = literal_tree
+        .then(|| Tree::read(&mut bits, 256))
+        .transpose()?;
+    let lengths = Tree::read(&mut bits, 64)?;
+    let distances = Tree::read(&mut bits, 64)?;
+
+    let distance_low_bits = if large_dictionary { 7 } else { 6 };
+    let min_length = if literal_tree { 3 } else { 2 };
+
+    let eof = || invalid_data("implode data ended early");
+
+    while output.len() < uncompressed_size {
+        let Some(is_literal) = bits.read_bit() else {
+            break;
+        };
+
+        if is_literal {
+            let byte = match &literals {
+                Some(tree) => tree.decode(&mut bits).ok_or_else(eof)? as u8,
+                None => bits.read(8).ok_or_else(eof)? as u8,
+            };
+
+            output.push(byte);
+
+            continue;
+        }
+
+        let low = bits.read(distance_low_bits).ok_or_else(eof)? as usize;
+        let high = distances.decode(&mut bits).ok_or_else(eof)? as usize;
+        let distance = ((high << distance_low_bits) | low) + 1;
+
+        let mut length = lengths.decode(&mut bits).ok_or_else(eof)? as usize;
+
+        // The largest length continues into the next byte.
+        if length == 63 {
+            length += bits.read(8).ok_or_else(eof)? as usize;
+        }
+
+        for _ in 0..length + min_length {
+            // Anything before the start of the output is zero.
+            let byte = output
+                .len()
+                .checked_sub(distance)
+                .map_or(0, |index| output[index]);
+
+            output.push(byte);
+        }
+    }
+
+    output.truncate(uncompressed_size);
+
+    Ok(output)
+}
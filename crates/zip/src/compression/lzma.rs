@@ -0,0 +1,116 @@
+//! LZMA
+//!
+//! The compressed data starts with a 2 byte LZMA SDK version and a 2 byte properties size, followed by the
+//! 5 byte properties. Unlike the .lzma format there's no uncompressed size. Instead general purpose bit 1
+//! says whether the data ends with an end of stream marker, otherwise it's sized from the headers.
+//!
+//! lzma-rs only accepts the end of stream marker once the input has run out, so the compressed size still has to
+//! be known.
+
+use std::io::{self, Write};
+
+use lzma_rs::decompress::{Options, Stream, UnpackedSize};
+
+use crate::{ArchiveLimits, Error};
+
+use super::{invalid_data, Decoded, Decoder};
+
+/// Limit the input per call since LZMA can expand a small amount of input into a lot of output.
+const INPUT_CHUNK_SIZE: usize = 4 * 1024;
+
+/// The version and properties size before the properties.
+const HEADER_SIZE: usize = 4;
+/// The properties byte (lc, lp and pb) and the dictionary size.
+const PROPERTIES_SIZE: u16 = 5;
+/// The header and properties, which are held back until the dictionary size has been checked.
+const PREFIX_SIZE: usize = HEADER_SIZE + PROPERTIES_SIZE as usize;
+
+pub(crate) struct LzmaDecoder {
+    /// The header and properties.
+    header: Vec<u8>,
+    stream: Option<Stream<Vec<u8>>>,
+    limits: ArchiveLimits,
+}
+
+impl LzmaDecoder {
+    /// Without an end of stream marker the `uncompressed_size` is where the data ends.
+    pub fn new(uncompressed_size: Option<u64>, limits: ArchiveLimits) -> Self {
+        let options = Options {
+            unpacked_size: UnpackedSize::UseProvided(uncompressed_size),
+            ..Options::default()
+        };
+
+        Self {
+            header: Vec::with_capacity(PREFIX_SIZE),
+            stream: Some(Stream::new_with_options(&options, Vec::new())),
+            limits,
+        }
+    }
+}
+
+impl Decoder for LzmaDecoder {
+    fn decode(&mut self, input: &[u8], eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Ok(Decoded {
+                consumed: 0,
+                finished: true,
+            });
+        };
+
+        let mut consumed = 0;
+
+        if self.header.len() < PREFIX_SIZE {
+            consumed = input.len().min(PREFIX_SIZE - self.header.len());
+
+            self.header.extend_from_slice(&input[..consumed]);
+
+            if self.header.len() < PREFIX_SIZE {
+                if eof && consumed == input.len() {
+                    return Err(invalid_data("LZMA header ended early"));
+                }
+
+                return Ok(Decoded {
+                    consumed,
+                    finished: false,
+                });
+            }
+
+            let properties_size = u16::from_le_bytes([self.header[2], self.header[3]]);
+
+            if properties_size != PROPERTIES_SIZE {
+                return Err(invalid_data("unexpected LZMA properties size"));
+            }
+
+            let dictionary_size = u32::from_le_bytes([
+                self.header[HEADER_SIZE + 1],
+                self.header[HEADER_SIZE + 2],
+                self.header[HEADER_SIZE + 3],
+                self.header[HEADER_SIZE + 4],
+            ]);
+
+            self.limits.check_decoder_memory(dictionary_size.into())?;
+
+            stream.write_all(&self.header[HEADER_SIZE..])?;
+        }
+
+        let remaining = &input[consumed..];
+
+        consumed += stream.write(&remaining[..remaining.len().min(INPUT_CHUNK_SIZE)])?;
+
+        if let Some(decoded) = stream.get_output_mut() {
+            output.append(decoded);
+        }
+
+        let finished = eof && consumed == input.len();
+
+        if finished {
+            if let Some(stream) = self.stream.take() {
+                let mut decoded = stream.finish().map_err(Error::Lzma)?;
+
+                output.append(&mut decoded);
+            }
+        }
+
+        Ok(Decoded { consumed, finished })
+    }
+}
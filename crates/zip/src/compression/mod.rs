@@ -0,0 +1,306 @@
+// The .ZIP File Format Specification documents the following compression methods:
+//     Store (no compression),
+//     Shrink (LZW),
+//     Reduce (levels 1–4; LZ77 + probabilistic),
+//     Implode,
+//     Deflate,
+//     Deflate64,
+//     bzip2,
+//     LZMA,
+//     WavPack,
+//     PPMd,
+//     LZ77 variant provided by IBM z/OS CMPSC instruction.
+// The most commonly used compression method is DEFLATE, which is described in IETF RFC 1951.
+
+// Other methods mentioned, but not documented in detail in the specification include:
+//     PKWARE DCL Implode (old IBM TERSE),
+//     new IBM TERSE, IBM LZ77 z Architecture (PFS),
+//     and a JPEG variant. A "Tokenize" method was reserved for a third party, but support was never added.
+
+// The word Implode is overused by PKWARE: the DCL/TERSE Implode is distinct from the old PKZIP Implode, a predecessor to Deflate.
+// The DCL Implode is undocumented partially due to its proprietary nature held by IBM, but Mark Adler has nevertheless provided a decompressor called "blast" alongside zlib.
+
+// 4.3.4 Compression MUST NOT be applied to a "local file header", an "encryption
+//    header", or an "end of central directory record".  Individual "central
+//    directory records" MUST NOT be compressed, but the aggregate of all central
+//    directory records MAY be compressed.
+
+// 4.4.5 compression method: (2 bytes)
+//     0 - The file is stored (no compression)
+//     1 - The file is Shrunk
+//     2 - The file is Reduced with compression factor 1
+//     3 - The file is Reduced with compression factor 2
+//     4 - The file is Reduced with compression factor 3
+//     5 - The file is Reduced with compression factor 4
+//     6 - The file is Imploded
+//     7 - Reserved for Tokenizing compression algorithm
+//     8 - The file is Deflated
+//     9 - Enhanced Deflating using Deflate64(tm)
+//     10 - PKWARE Data Compression Library Imploding (old IBM TERSE)
+//     11 - Reserved by PKWARE
+//     12 - File is compressed using BZIP2 algorithm
+//     13 - Reserved by PKWARE
+//     14 - LZMA
+//     15 - Reserved by PKWARE
+//     16 - IBM z/OS CMPSC Compression
+//     17 - Reserved by PKWARE
+//     18 - File is compressed using IBM TERSE (new)
+//     19 - IBM LZ77 z Architecture
+//     20 - deprecated (use method 93 for zstd)
+//     93 - Zstandard (zstd) Compression
+//     94 - MP3 Compression
+//     95 - XZ Compression
+//     96 - JPEG variant
+//     97 - WavPack compressed data
+//     98 - PPMd version I, Rev 1
+//     99 - AE-x encryption marker (see APPENDIX E)
+
+use std::io;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::{
+    ArchiveLimits, Error, Result, GP_FLAG_IMPLODE_3_TREES, GP_FLAG_IMPLODE_8K_DICTIONARY,
+    GP_FLAG_LZMA_END_MARKER,
+};
+
+mod bits;
+mod bzip2;
+mod deflate;
+mod deflate64;
+mod implode;
+mod lzma;
+mod ppmd;
+mod reduce;
+mod shrink;
+mod store;
+#[cfg(feature = "xz")]
+mod xz;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+use self::{
+    bzip2::Bzip2Decoder,
+    deflate::{DeflateDecoder, DeflateEncoder},
+    deflate64::Deflate64Decoder,
+    lzma::LzmaDecoder,
+    ppmd::PpmdDecoder,
+    store::{StoreDecoder, StoreEncoder},
+};
+
+#[cfg(feature = "xz")]
+use self::xz::XzDecoder;
+#[cfg(feature = "zstd")]
+use self::zstd::ZstdDecoder;
+
+/// How much a decoder should try to output each time it's called.
+pub(crate) const OUTPUT_CHUNK_SIZE: usize = 32 * 1024;
+/// Most the legacy methods allocate up front. The uncompressed size comes from the headers so it can't be trusted.
+const MAX_OUTPUT_CAPACITY: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u16)]
+pub enum CompressionType {
+    None = 0,
+    Shrunk,
+    ReducedCF1,
+    ReducedCF2,
+    ReducedCF3,
+    ReducedCF4,
+    Imploded,
+    Tokenizing,
+    Deflate,
+    Deflate64,
+    IbmTerseOld,
+    Bzip2 = 12,
+    Lzma = 14,
+    IbmZosCmpsc = 16,
+    IbmTerseNew = 18,
+    IbmLz77z = 19,
+    /// Older writers used 20 for Zstandard before 93 was assigned.
+    DeprecatedZstd = 20,
+    Zstd = 93,
+    Mp3,
+    Xz,
+    Jpeg,
+    WavPack,
+    PPMd,
+    Aex,
+}
+
+impl CompressionType {
+    /// Does the compressed data mark its own end. Required when the compressed size isn't known up front.
+    ///
+    /// LZMA only does when the `gp_flag` says it has an end of stream marker.
+    pub(crate) fn has_end_marker(self, gp_flag: u16) -> bool {
+        match self {
+            Self::Deflate | Self::Deflate64 | Self::DeprecatedZstd | Self::Zstd | Self::Xz => true,
+            Self::Lzma => gp_flag & GP_FLAG_LZMA_END_MARKER != 0,
+            _ => false,
+        }
+    }
+
+    /// Create an incremental decoder for the compression method.
+    ///
+    /// The legacy methods and PPMd don't mark their end so they need the `uncompressed_size`, as does LZMA
+    /// without an end of stream marker. Implode and LZMA also use the `gp_flag`. PPMd, LZMA, XZ and Zstandard check
+    /// the memory they need against the `limits` once they've read it from the data.
+    pub(crate) fn decoder(
+        self,
+        gp_flag: u16,
+        uncompressed_size: Option<u64>,
+        limits: ArchiveLimits,
+    ) -> Result<Box<dyn Decoder>> {
+        Ok(match self {
+            Self::None => Box::new(StoreDecoder),
+
+            Self::Shrunk => {
+                let size = uncompressed_size.ok_or(Error::UnknownEntrySize(self))?;
+
+                Box::new(WholeInputDecoder::new(move |input| {
+                    shrink::unshrink(input, size)
+                }))
+            }
+
+            Self::ReducedCF1 | Self::ReducedCF2 | Self::ReducedCF3 | Self::ReducedCF4 => {
+                let size = uncompressed_size.ok_or(Error::UnknownEntrySize(self))?;
+                let factor = u16::from(self) as u8 - 1;
+
+                Box::new(WholeInputDecoder::new(move |input| {
+                    reduce::unreduce(input, factor, size)
+                }))
+            }
+
+            Self::Imploded => {
+                let size = uncompressed_size.ok_or(Error::UnknownEntrySize(self))?;
+                let large_dictionary = gp_flag & GP_FLAG_IMPLODE_8K_DICTIONARY != 0;
+                let literal_tree = gp_flag & GP_FLAG_IMPLODE_3_TREES != 0;
+
+                Box::new(WholeInputDecoder::new(move |input| {
+                    implode::explode(input, large_dictionary, literal_tree, size)
+                }))
+            }
+
+            Self::Deflate => Box::new(DeflateDecoder::new()),
+
+            Self::Deflate64 => Box::new(Deflate64Decoder::new()),
+
+            Self::Bzip2 => Box::new(Bzip2Decoder::new()),
+
+            Self::Lzma => {
+                let has_end_marker = gp_flag & GP_FLAG_LZMA_END_MARKER != 0;
+
+                Box::new(LzmaDecoder::new(
+                    if has_end_marker {
+                        None
+                    } else {
+                        Some(uncompressed_size.ok_or(Error::UnknownEntrySize(self))?)
+                    },
+                    limits,
+                ))
+            }
+
+            Self::PPMd => Box::new(PpmdDecoder::new(
+                uncompressed_size.ok_or(Error::UnknownEntrySize(self))?,
+                limits,
+            )),
+
+            #[cfg(feature = "zstd")]
+            Self::DeprecatedZstd | Self::Zstd => Box::new(ZstdDecoder::new(limits)?),
+
+            #[cfg(feature = "xz")]
+            Self::Xz => Box::new(XzDecoder::new(limits)?),
+
+            v => return Err(Error::UnsupportedCompression(v)),
+        })
+    }
+
+    /// Create an incremental encoder for the compression method.
+    ///
+    /// `level` is 0-9 for Deflate. It's ignored by Store.
+    pub(crate) fn encoder(self, level: Option<u32>) -> Result<Box<dyn Encoder>> {
+        Ok(match self {
+            Self::None => Box::new(StoreEncoder),
+
+            Self::Deflate => Box::new(DeflateEncoder::new(level)),
+
+            v => return Err(Error::UnsupportedCompression(v)),
+        })
+    }
+}
+
+/// The result of a single [`Decoder::decode`] call.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Decoded {
+    /// Amount of input which was used.
+    pub consumed: usize,
+    /// The end of the compressed stream has been reached.
+    pub finished: bool,
+}
+
+/// Decompresses a stream incrementally. Compressed data is pushed in and the decompressed bytes are appended to the output.
+pub(crate) trait Decoder: Send {
+    /// Decompress from the start of `input`, appending to `output`.
+    ///
+    /// `eof` is set once `input` holds the last of the compressed data.
+    /// Returning nothing consumed and nothing output means more input is needed.
+    fn decode(&mut self, input: &[u8], eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded>;
+}
+
+/// Collects all of the compressed data, then decompresses it in one go.
+///
+/// Used by the legacy methods. They come from a time when files were small enough for this to be fine.
+pub(crate) struct WholeInputDecoder<F> {
+    input: Vec<u8>,
+    decompress: Option<F>,
+}
+
+impl<F> WholeInputDecoder<F>
+where
+    F: FnOnce(&[u8]) -> io::Result<Vec<u8>> + Send,
+{
+    pub fn new(decompress: F) -> Self {
+        Self {
+            input: Vec::new(),
+            decompress: Some(decompress),
+        }
+    }
+}
+
+impl<F> Decoder for WholeInputDecoder<F>
+where
+    F: FnOnce(&[u8]) -> io::Result<Vec<u8>> + Send,
+{
+    fn decode(&mut self, input: &[u8], eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        self.input.extend_from_slice(input);
+
+        if eof {
+            if let Some(decompress) = self.decompress.take() {
+                output.append(&mut decompress(&self.input)?);
+            }
+        }
+
+        Ok(Decoded {
+            consumed: input.len(),
+            finished: eof,
+        })
+    }
+}
+
+/// What to allocate for the output of a legacy method.
+pub(crate) fn output_capacity(uncompressed_size: u64) -> usize {
+    uncompressed_size.min(MAX_OUTPUT_CAPACITY) as usize
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Compresses a stream incrementally. The compressed bytes are appended to the output.
+pub(crate) trait Encoder: Send {
+    /// Compress all of `input`, appending to `output`.
+    fn encode(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Output the rest of the compressed data and end the stream.
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()>;
+}
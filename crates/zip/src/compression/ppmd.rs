@@ -0,0 +1,131 @@
+//! PPMd variant I revision 1
+//!
+//! The stream starts with 2 bytes of parameters: the model order - 1 (bits 0-3), the memory size in MB - 1 (bits 4-11)
+//! and the model restoration method (bits 12-15). There's no end marker so the uncompressed size is needed.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+};
+
+use ppmd_rust::{Ppmd8Decoder, RestoreMethod, PPMD8_MIN_ORDER};
+
+use crate::ArchiveLimits;
+
+use super::{invalid_data, Decoded, Decoder, OUTPUT_CHUNK_SIZE};
+
+const PARAMETERS_SIZE: usize = 2;
+
+/// The decoder reads its input as it goes, so a symbol is only decoded once there's enough input for any symbol.
+/// Each one can escape through every order, reading up to 4 bytes each time.
+const MAX_INPUT_PER_SYMBOL: usize = 128;
+
+/// How much input is held onto for the decoder.
+const INPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+pub(crate) struct PpmdDecoder {
+    /// Input collected before the decoder is created.
+    pending: VecDeque<u8>,
+    inner: Option<Ppmd8Decoder<VecDeque<u8>>>,
+    remaining: u64,
+    /// The memory size comes from the data, so it's checked before anything is allocated.
+    limits: ArchiveLimits,
+}
+
+impl PpmdDecoder {
+    pub fn new(uncompressed_size: u64, limits: ArchiveLimits) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            inner: None,
+            remaining: uncompressed_size,
+            limits,
+        }
+    }
+
+    fn create_inner(&mut self) -> io::Result<Ppmd8Decoder<VecDeque<u8>>> {
+        if self.pending.len() < PARAMETERS_SIZE {
+            return Err(invalid_data("missing ppmd parameters"));
+        }
+
+        let parameters = u16::from_le_bytes([self.pending[0], self.pending[1]]);
+        self.pending.drain(..PARAMETERS_SIZE);
+
+        let order = (parameters & 0xF) as u32 + 1;
+        let memory_size = (((parameters >> 4) & 0xFF) as u32 + 1) << 20;
+        let restore_method = RestoreMethod::from(parameters >> 12);
+
+        if order < PPMD8_MIN_ORDER || restore_method == RestoreMethod::Unsupported {
+            return Err(invalid_data("unsupported ppmd parameters"));
+        }
+
+        self.limits.check_decoder_memory(memory_size.into())?;
+
+        Ppmd8Decoder::new(
+            std::mem::take(&mut self.pending),
+            order,
+            memory_size,
+            restore_method,
+        )
+        .map_err(|e| invalid_data(&e.to_string()))
+    }
+}
+
+impl Decoder for PpmdDecoder {
+    fn decode(&mut self, input: &[u8], eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        let buffered = match &self.inner {
+            Some(inner) => inner.get_ref(),
+            None => &self.pending,
+        };
+
+        let consumed = input
+            .len()
+            .min(INPUT_BUFFER_SIZE.saturating_sub(buffered.len()));
+
+        // Once all of the input is held there's no need to keep any back.
+        let is_last = eof && consumed == input.len();
+
+        let inner = match &mut self.inner {
+            Some(inner) => {
+                inner.get_mut().extend(&input[..consumed]);
+                inner
+            }
+
+            None => {
+                self.pending.extend(&input[..consumed]);
+
+                if !is_last && self.pending.len() < PARAMETERS_SIZE + MAX_INPUT_PER_SYMBOL {
+                    return Ok(Decoded {
+                        consumed,
+                        finished: false,
+                    });
+                }
+
+                let inner = self.create_inner()?;
+                self.inner.insert(inner)
+            }
+        };
+
+        let mut byte = [0u8; 1];
+        let mut amount = 0;
+
+        while self.remaining != 0 && amount < OUTPUT_CHUNK_SIZE {
+            if !is_last && inner.get_ref().len() < MAX_INPUT_PER_SYMBOL {
+                break;
+            }
+
+            // Running out of input ends the stream early, which gets caught by the size check.
+            if inner.read(&mut byte)? == 0 {
+                break;
+            }
+
+            output.push(byte[0]);
+            amount += 1;
+            self.remaining -= 1;
+        }
+
+        Ok(Decoded {
+            consumed,
+            finished: self.remaining == 0 || (is_last && amount == 0),
+        })
+    }
+}
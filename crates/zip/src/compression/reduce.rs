@@ -0,0 +1,166 @@
+//! Reduce (compression factors 1-4)
+//!
+//! Each byte is predicted from a set of up to 32 followers of the previous byte. The result is then expanded
+//! by a small state machine where 144 (DLE) introduces a length and distance to copy from.
+
+use std::io;
+
+use super::{bits::BitReader, invalid_data, output_capacity};
+
+/// Introduces a copy, or a literal 144 when followed by 0.
+const DLE: u8 = 144;
+
+const MAX_FOLLOWERS: usize = 32;
+
+/// Bits used for an index into a follower set of `count` bytes.
+fn follower_index_bits(count: usize) -> usize {
+    match count {
+        0 => 0,
+        1..=2 => 1,
+        3..=4 => 2,
+        5..=8 => 3,
+        9..=16 => 4,
+        _ => 5,
+    }
+}
+
+struct FollowerSets {
+    sets: [[u8; MAX_FOLLOWERS]; 256],
+    counts: [usize; 256],
+}
+
+impl FollowerSets {
+    fn read(bits: &mut BitReader<'_>) -> io::Result<Self> {
+        let mut this = Self {
+            sets: [[0; MAX_FOLLOWERS]; 256],
+            counts: [0; 256],
+        };
+
+        let eof = || invalid_data("reduce follower sets ended early");
+
+        // Stored from the last byte to the first.
+        for byte in (0..256).rev() {
+            let count = bits.read(6).ok_or_else(eof)? as usize;
+
+            if count > MAX_FOLLOWERS {
+                return Err(invalid_data("reduce follower set is too large"));
+            }
+
+            for follower in &mut this.sets[byte][..count] {
+                *follower = bits.read(8).ok_or_else(eof)? as u8;
+            }
+
+            this.counts[byte] = count;
+        }
+
+        Ok(this)
+    }
+
+    /// Read the next byte following `last`. Returns `None` once the data runs out.
+    fn next(&self, bits: &mut BitReader<'_>, last: u8) -> io::Result<Option<u8>> {
+        let count = self.counts[last as usize];
+
+        // With followers the next bit says whether the byte is one of them.
+        let is_literal = match count {
+            0 => true,
+            _ => match bits.read_bit() {
+                Some(v) => v,
+                None => return Ok(None),
+            },
+        };
+
+        if is_literal {
+            return Ok(bits.read(8).map(|v| v as u8));
+        }
+
+        let Some(index) = bits.read(follower_index_bits(count)) else {
+            return Ok(None);
+        };
+
+        self.sets[last as usize][..count]
+            .get(index as usize)
+            .copied()
+            .map(Some)
+            .ok_or_else(|| invalid_data("reduce follower index is out of range"))
+    }
+}
+
+enum State {
+    Literal,
+    Dle,
+    /// Holds the length so far and the byte it came from.
+    Length(usize, u8),
+    Distance(usize, u8),
+}
+
+/// `factor` is 1-4, from compression methods 2-5.
+pub(crate) fn unreduce(input: &[u8], factor: u8, uncompressed_size: u64) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(output_capacity(uncompressed_size));
+
+    let uncompressed_size = uncompressed_size as usize;
+
+    let mut bits = BitReader::new(input);
+    let followers = FollowerSets::read(&mut bits)?;
+
+    // The length is held in the low bits of the byte after the DLE. The rest are the high distance bits.
+    let length_bits = 8 - factor as u32;
+    let length_mask = (1u8 << length_bits) - 1;
+
+    let mut last = 0;
+    let mut state = State::Literal;
+
+    while output.len() < uncompressed_size {
+        let Some(byte) = followers.next(&mut bits, last)? else {
+            break;
+        };
+
+        last = byte;
+
+        state = match state {
+            State::Literal if byte == DLE => State::Dle,
+
+            State::Literal => {
+                output.push(byte);
+                State::Literal
+            }
+
+            State::Dle if byte == 0 => {
+                output.push(DLE);
+                State::Literal
+            }
+
+            State::Dle => {
+                let length = (byte & length_mask) as usize;
+
+                // The largest length continues into the next byte.
+                if byte & length_mask == length_mask {
+                    State::Length(length, byte)
+                } else {
+                    State::Distance(length, byte)
+                }
+            }
+
+            State::Length(length, value) => State::Distance(length + byte as usize, value),
+
+            State::Distance(length, value) => {
+                let distance = ((value >> length_bits) as usize) * 256 + byte as usize + 1;
+
+                for _ in 0..length + 3 {
+                    // Anything before the start of the output is zero.
+                    let byte = output
+                        .len()
+                        .checked_sub(distance)
+                        .map_or(0, |index| output[index]);
+
+                    output.push(byte);
+                }
+
+                State::Literal
+            }
+        };
+    }
+
+    output.truncate(uncompressed_size);
+
+    Ok(output)
+}
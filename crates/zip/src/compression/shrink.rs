@@ -0,0 +1,177 @@
+//! Shrink (LZW)
+//!
+//! Codes start at 9 bits and grow to 13. Code 256 is followed by a control code, 1 to grow the code size
+//! or 2 to partially clear the table of every code which isn't a prefix of another.
+
+use std::io;
+
+use super::{bits::BitReader, invalid_data, output_capacity};
+
+const MIN_CODE_SIZE: usize = 9;
+const MAX_CODE_SIZE: usize = 13;
+const MAX_CODE: usize = (1 << MAX_CODE_SIZE) - 1;
+
+const CONTROL_CODE: u16 = 256;
+const CONTROL_INCREASE_CODE_SIZE: u32 = 1;
+const CONTROL_PARTIAL_CLEAR: u32 = 2;
+
+/// Marks a free code.
+const FREE: u16 = u16::MAX;
+
+#[derive(Clone, Copy)]
+struct Code {
+    prefix: u16,
+    /// Length of the string the code represents.
+    len: usize,
+    /// Where the string was last output. New codes extend a string which was just output so they can copy from here.
+    position: usize,
+}
+
+struct Table {
+    codes: Vec<Code>,
+    /// Free codes in ascending order. The lowest is used next.
+    free: Vec<u16>,
+}
+
+impl Table {
+    fn new() -> Self {
+        let codes = (0..=MAX_CODE)
+            .map(|v| Code {
+                prefix: if v < CONTROL_CODE as usize {
+                    v as u16
+                } else {
+                    FREE
+                },
+                len: 1,
+                position: 0,
+            })
+            .collect();
+
+        Self {
+            codes,
+            free: (CONTROL_CODE + 1..=MAX_CODE as u16).rev().collect(),
+        }
+    }
+
+    fn next_free(&self) -> Option<u16> {
+        self.free.last().copied()
+    }
+
+    fn partial_clear(&mut self) {
+        let mut is_prefix = vec![false; MAX_CODE + 1];
+
+        for code in &self.codes[CONTROL_CODE as usize + 1..] {
+            if code.prefix != FREE {
+                is_prefix[code.prefix as usize] = true;
+            }
+        }
+
+        self.free.clear();
+
+        for value in (CONTROL_CODE as usize + 1..=MAX_CODE).rev() {
+            if !is_prefix[value] {
+                self.codes[value].prefix = FREE;
+                self.free.push(value as u16);
+            }
+        }
+    }
+}
+
+/// Read the next code, handling any control codes before it. Returns `None` once the data runs out.
+fn read_code(
+    bits: &mut BitReader<'_>,
+    code_size: &mut usize,
+    table: &mut Table,
+) -> io::Result<Option<u16>> {
+    loop {
+        let Some(code) = bits.read(*code_size) else {
+            return Ok(None);
+        };
+
+        if code != CONTROL_CODE as u32 {
+            return Ok(Some(code as u16));
+        }
+
+        match bits.read(*code_size) {
+            Some(CONTROL_INCREASE_CODE_SIZE) if *code_size < MAX_CODE_SIZE => *code_size += 1,
+            Some(CONTROL_PARTIAL_CLEAR) => table.partial_clear(),
+            Some(_) => return Err(invalid_data("invalid shrink control code")),
+            None => return Ok(None),
+        }
+    }
+}
+
+pub(crate) fn unshrink(input: &[u8], uncompressed_size: u64) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(output_capacity(uncompressed_size));
+
+    let mut bits = BitReader::new(input);
+    let mut code_size = MIN_CODE_SIZE;
+    let mut table = Table::new();
+
+    let Some(first) = read_code(&mut bits, &mut code_size, &mut table)? else {
+        return Ok(output);
+    };
+
+    if first >= CONTROL_CODE {
+        return Err(invalid_data("first shrink code isn't a literal"));
+    }
+
+    table.codes[first as usize].position = 0;
+    output.push(first as u8);
+
+    let mut prev = first as usize;
+
+    while (output.len() as u64) < uncompressed_size {
+        let Some(code) = read_code(&mut bits, &mut code_size, &mut table)? else {
+            break;
+        };
+
+        let code = code as usize;
+
+        // The code is the one about to be added: the previous string followed by its own first byte.
+        if table.next_free() == Some(code as u16) {
+            if table.codes[prev].prefix == FREE {
+                return Err(invalid_data("shrink code refers to a cleared code"));
+            }
+
+            table.codes[code] = Code {
+                prefix: prev as u16,
+                len: table.codes[prev].len + 1,
+                position: table.codes[prev].position,
+            };
+        }
+
+        if table.codes[code].prefix == FREE {
+            return Err(invalid_data("shrink code isn't in the table"));
+        }
+
+        let position = output.len();
+
+        if code < CONTROL_CODE as usize {
+            output.push(code as u8);
+        } else {
+            let Code { len, position, .. } = table.codes[code];
+
+            // Copying a byte at a time lets the string overlap with where it's being written.
+            for i in position..position + len {
+                output.push(output[i]);
+            }
+        }
+
+        // The previous string extended by the first byte of this one.
+        if let Some(new) = table.free.pop() {
+            table.codes[new as usize] = Code {
+                prefix: prev as u16,
+                len: table.codes[prev].len + 1,
+                position: table.codes[prev].position,
+            };
+        }
+
+        table.codes[code].position = position;
+        prev = code;
+    }
+
+    output.truncate(uncompressed_size as usize);
+
+    Ok(output)
+}
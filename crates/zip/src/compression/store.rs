@@ -0,0 +1,34 @@
+//! Stored (no compression)
+
+use std::io;
+
+use super::{Decoded, Decoder, Encoder, OUTPUT_CHUNK_SIZE};
+
+pub(crate) struct StoreDecoder;
+
+impl Decoder for StoreDecoder {
+    fn decode(&mut self, input: &[u8], eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        let amount = input.len().min(OUTPUT_CHUNK_SIZE);
+
+        output.extend_from_slice(&input[..amount]);
+
+        Ok(Decoded {
+            consumed: amount,
+            finished: eof && amount == input.len(),
+        })
+    }
+}
+
+pub(crate) struct StoreEncoder;
+
+impl Encoder for StoreEncoder {
+    fn encode(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        output.extend_from_slice(input);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _output: &mut Vec<u8>) -> io::Result<()> {
+        Ok(())
+    }
+}
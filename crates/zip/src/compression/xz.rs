@@ -0,0 +1,55 @@
+//! XZ
+
+use std::io;
+
+use xz2::stream::{Action, Error as XzError, Status, Stream};
+
+use crate::{ArchiveLimits, Error};
+
+use super::{Decoded, Decoder, OUTPUT_CHUNK_SIZE};
+
+pub(crate) struct XzDecoder {
+    inner: Stream,
+    /// Passed to liblzma as its memory limit.
+    max_memory: Option<u64>,
+}
+
+impl XzDecoder {
+    pub fn new(limits: ArchiveLimits) -> io::Result<Self> {
+        let max_memory = limits.max_decoder_memory;
+
+        Ok(Self {
+            inner: Stream::new_stream_decoder(max_memory.unwrap_or(u64::MAX), 0)?,
+            max_memory,
+        })
+    }
+}
+
+impl Decoder for XzDecoder {
+    fn decode(&mut self, input: &[u8], _eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        // process_vec only writes into the spare capacity.
+        output.reserve(OUTPUT_CHUNK_SIZE);
+
+        let total_in = self.inner.total_in();
+
+        let status = match self.inner.process_vec(input, output, Action::Run) {
+            Ok(v) => v,
+
+            // liblzma checks its memory use as it reads the block headers.
+            Err(XzError::MemLimit) => {
+                return Err(Error::DecoderMemoryExceeded {
+                    limit: self.max_memory.unwrap_or(u64::MAX),
+                    found: None,
+                }
+                .into())
+            }
+
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Decoded {
+            consumed: (self.inner.total_in() - total_in) as usize,
+            finished: status == Status::StreamEnd,
+        })
+    }
+}
@@ -0,0 +1,144 @@
+//! Zstandard (RFC 8878)
+
+use std::io;
+
+use zstd::stream::raw::{DParameter, Decoder as InnerDecoder, Operation};
+
+use crate::ArchiveLimits;
+
+use super::{Decoded, Decoder, OUTPUT_CHUNK_SIZE};
+
+const FRAME_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+/// Frame header descriptor bit 5: there's no window descriptor, the window is the whole content.
+const SINGLE_SEGMENT_FLAG: u8 = 1 << 5;
+/// Smallest and largest window log zstd accepts as a limit on 64-bit platforms.
+const WINDOW_LOG_RANGE: (u32, u32) = (10, 31);
+
+pub(crate) struct ZstdDecoder {
+    inner: InnerDecoder<'static>,
+    /// The last frame has been decoded and flushed. Another frame could still follow it.
+    frame_done: bool,
+    /// The next input starts a frame, whose window is checked against the limits.
+    frame_start: bool,
+    limits: ArchiveLimits,
+}
+
+impl ZstdDecoder {
+    pub fn new(limits: ArchiveLimits) -> io::Result<Self> {
+        let mut inner = InnerDecoder::new()?;
+
+        // zstd only takes a power of 2, so the window is checked more precisely from the frame header too.
+        if let Some(limit) = limits.max_decoder_memory {
+            let window_log = (u64::BITS - 1).saturating_sub(limit.leading_zeros());
+
+            inner.set_parameter(DParameter::WindowLogMax(
+                window_log.clamp(WINDOW_LOG_RANGE.0, WINDOW_LOG_RANGE.1),
+            ))?;
+        }
+
+        Ok(Self {
+            inner,
+            frame_done: false,
+            frame_start: true,
+            limits,
+        })
+    }
+}
+
+/// What the start of a frame says about how much history the decoder has to keep.
+enum Window {
+    /// More of the frame header is needed.
+    Incomplete,
+    Size(u64),
+    /// A skippable frame, or not a frame at all which zstd will reject itself.
+    Unknown,
+}
+
+impl Window {
+    /// Read the window size from the frame header at the start of `input`. See RFC 8878 section 3.1.1.1.
+    fn from_frame(input: &[u8]) -> Self {
+        let Some((magic, &descriptor)) = input.get(..4).zip(input.get(4)) else {
+            return Self::Incomplete;
+        };
+
+        if magic != FRAME_MAGIC {
+            return Self::Unknown;
+        }
+
+        if descriptor & SINGLE_SEGMENT_FLAG == 0 {
+            let Some(&window_descriptor) = input.get(5) else {
+                return Self::Incomplete;
+            };
+
+            let window_base = 1u64 << (10 + (window_descriptor >> 3));
+
+            return Self::Size(window_base + (window_base / 8) * u64::from(window_descriptor & 7));
+        }
+
+        let dictionary_id_size = [0, 1, 2, 4][(descriptor & 3) as usize];
+        let content_size_size = [1, 2, 4, 8][(descriptor >> 6) as usize];
+
+        let Some(content_size) =
+            input.get(5 + dictionary_id_size..5 + dictionary_id_size + content_size_size)
+        else {
+            return Self::Incomplete;
+        };
+
+        let size = content_size
+            .iter()
+            .rev()
+            .fold(0u64, |value, byte| (value << 8) | u64::from(*byte));
+
+        // The 2 byte size is offset by 256.
+        Self::Size(if content_size_size == 2 {
+            size + 256
+        } else {
+            size
+        })
+    }
+}
+
+impl Decoder for ZstdDecoder {
+    fn decode(&mut self, input: &[u8], eof: bool, output: &mut Vec<u8>) -> io::Result<Decoded> {
+        // Decoding nothing would start looking for the next frame.
+        if input.is_empty() && eof && self.frame_done {
+            return Ok(Decoded {
+                consumed: 0,
+                finished: true,
+            });
+        }
+
+        if self.frame_start {
+            match Window::from_frame(input) {
+                Window::Incomplete if !eof => {
+                    return Ok(Decoded {
+                        consumed: 0,
+                        finished: false,
+                    })
+                }
+
+                Window::Size(size) => self.limits.check_decoder_memory(size)?,
+
+                _ => {}
+            }
+
+            self.frame_start = false;
+        }
+
+        let start = output.len();
+        output.resize(start + OUTPUT_CHUNK_SIZE, 0);
+
+        let status = self.inner.run_on_buffers(input, &mut output[start..])?;
+
+        output.truncate(start + status.bytes_written);
+
+        // A hint of 0 means the frame has been decoded and flushed.
+        self.frame_done = status.remaining == 0;
+        self.frame_start = self.frame_done;
+
+        Ok(Decoded {
+            consumed: status.bytes_read,
+            finished: self.frame_done && eof && status.bytes_read == input.len(),
+        })
+    }
+}
@@ -0,0 +1,127 @@
+//! WinZip AES Encryption (AE-1 and AE-2)
+//!
+//! The entry data is made up of a salt, a 2 byte password verification value, the encrypted data and a 10 byte authentication code.
+//! The keys and verification value are derived from the password and salt using PBKDF2-HMAC-SHA1 with 1000 iterations.
+//! The data is encrypted using AES in CTR mode with a little endian counter which starts at 1.
+//! The authentication code is the first 10 bytes of the HMAC-SHA1 of the encrypted data.
+
+use aes::{
+    cipher::{generic_array::GenericArray, KeyIvInit, StreamCipher},
+    Aes128, Aes192, Aes256,
+};
+use ctr::Ctr128LE;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use crate::{Error, Result};
+
+use super::Decryptor;
+
+/// Size of the password verification value which follows the salt.
+pub(crate) const AES_PASSWORD_VERIFIER_SIZE: usize = 2;
+/// Size of the authentication code which follows the encrypted data.
+pub(crate) const AES_AUTHENTICATION_CODE_SIZE: usize = 10;
+
+const KEY_ITERATIONS: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AesStrength {
+    Aes128,
+    Aes192,
+    Aes256,
+}
+
+impl AesStrength {
+    /// From the strength value in the AES extra field.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            1 => Self::Aes128,
+            2 => Self::Aes192,
+            3 => Self::Aes256,
+            _ => return None,
+        })
+    }
+
+    pub fn key_size(self) -> usize {
+        match self {
+            Self::Aes128 => 16,
+            Self::Aes192 => 24,
+            Self::Aes256 => 32,
+        }
+    }
+
+    /// The salt is half the size of the key.
+    pub fn salt_size(self) -> usize {
+        self.key_size() / 2
+    }
+}
+
+enum AesCtr {
+    Aes128(Ctr128LE<Aes128>),
+    Aes192(Ctr128LE<Aes192>),
+    Aes256(Ctr128LE<Aes256>),
+}
+
+pub(crate) struct AesDecryptor {
+    cipher: AesCtr,
+    hmac: Hmac<Sha1>,
+    /// What the HMAC of the encrypted data should start with.
+    authentication_code: [u8; AES_AUTHENTICATION_CODE_SIZE],
+}
+
+impl AesDecryptor {
+    /// Derive the keys from the password. Returns `None` if the password verification value doesn't match.
+    pub fn new(
+        password: &[u8],
+        strength: AesStrength,
+        salt: &[u8],
+        verifier: [u8; AES_PASSWORD_VERIFIER_SIZE],
+        authentication_code: [u8; AES_AUTHENTICATION_CODE_SIZE],
+    ) -> Option<Self> {
+        let key_size = strength.key_size();
+
+        let mut derived = vec![0u8; key_size * 2 + AES_PASSWORD_VERIFIER_SIZE];
+        pbkdf2::pbkdf2_hmac::<Sha1>(password, salt, KEY_ITERATIONS, &mut derived);
+
+        let (encryption_key, rest) = derived.split_at(key_size);
+        let (authentication_key, derived_verifier) = rest.split_at(key_size);
+
+        if derived_verifier != verifier {
+            return None;
+        }
+
+        let iv = GenericArray::from(1u128.to_le_bytes());
+
+        let cipher = match strength {
+            AesStrength::Aes128 => AesCtr::Aes128(Ctr128LE::new(encryption_key.into(), &iv)),
+            AesStrength::Aes192 => AesCtr::Aes192(Ctr128LE::new(encryption_key.into(), &iv)),
+            AesStrength::Aes256 => AesCtr::Aes256(Ctr128LE::new(encryption_key.into(), &iv)),
+        };
+
+        Some(Self {
+            cipher,
+            hmac: Hmac::new_from_slice(authentication_key).ok()?,
+            authentication_code,
+        })
+    }
+}
+
+impl Decryptor for AesDecryptor {
+    fn decrypt(&mut self, data: &mut [u8]) {
+        // The authentication code covers the encrypted data.
+        self.hmac.update(data);
+
+        match &mut self.cipher {
+            AesCtr::Aes128(v) => v.apply_keystream(data),
+            AesCtr::Aes192(v) => v.apply_keystream(data),
+            AesCtr::Aes256(v) => v.apply_keystream(data),
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.hmac
+            .clone()
+            .verify_truncated_left(&self.authentication_code)
+            .map_err(|_| Error::AuthenticationMismatch)
+    }
+}
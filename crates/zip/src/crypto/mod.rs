@@ -0,0 +1,19 @@
+//! Encryption methods for entry data.
+
+mod aes;
+mod zip_crypto;
+
+pub(crate) use aes::*;
+pub(crate) use zip_crypto::*;
+
+use crate::Result;
+
+/// Decrypts the entry data as it's read from the archive, before it's decompressed.
+pub(crate) trait Decryptor: Send {
+    fn decrypt(&mut self, data: &mut [u8]);
+
+    /// Called once all of the data has been decrypted to check it hasn't been tampered with.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
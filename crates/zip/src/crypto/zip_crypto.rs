@@ -0,0 +1,102 @@
+//! Traditional PKWARE Encryption (ZipCrypto)
+//!
+//! 6.1.5 Each encrypted file has an extra 12 bytes stored at the start of the data area defining the encryption header for that file.
+//! The encryption header is originally set to random values, and then itself encrypted, using three, 32-bit keys.
+//! The key values are initialized using the supplied encryption password.
+//! After each byte is encrypted, the keys are then updated using pseudo-random number generation techniques in combination with the same CRC-32 algorithm used in PKZIP.
+//!
+//! This method is weak. It's only supported for reading older archives.
+
+use super::Decryptor;
+
+/// Size of the encryption header which is placed before the encrypted data.
+pub(crate) const ZIP_CRYPTO_HEADER_SIZE: usize = 12;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+fn crc32_byte(crc: u32, value: u8) -> u32 {
+    (crc >> 8) ^ CRC_TABLE[((crc ^ value as u32) & 0xFF) as usize]
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ZipCryptoKeys {
+    key_0: u32,
+    key_1: u32,
+    key_2: u32,
+}
+
+impl ZipCryptoKeys {
+    pub fn new(password: &[u8]) -> Self {
+        let mut this = Self {
+            key_0: 0x1234_5678,
+            key_1: 0x2345_6789,
+            key_2: 0x3456_7890,
+        };
+
+        for &value in password {
+            this.update(value);
+        }
+
+        this
+    }
+
+    fn update(&mut self, value: u8) {
+        self.key_0 = crc32_byte(self.key_0, value);
+        self.key_1 = self
+            .key_1
+            .wrapping_add(self.key_0 & 0xFF)
+            .wrapping_mul(134_775_813)
+            .wrapping_add(1);
+        self.key_2 = crc32_byte(self.key_2, (self.key_1 >> 24) as u8);
+    }
+
+    fn stream_byte(&self) -> u8 {
+        let temp = (self.key_2 | 2) & 0xFFFF;
+
+        (temp.wrapping_mul(temp ^ 1) >> 8) as u8
+    }
+
+    /// Decrypt the encryption header. Returns the last byte which is used to check the password.
+    ///
+    /// 6.1.6 After the header is decrypted, the last 1 or 2 bytes in Buffer SHOULD be the high-order word/byte of the CRC for the file being decrypted.
+    /// When bit 3 of the general purpose flag is set the CRC isn't known, so the high byte of the file time is used instead.
+    pub fn decrypt_header(&mut self, mut header: [u8; ZIP_CRYPTO_HEADER_SIZE]) -> u8 {
+        self.decrypt(&mut header);
+
+        header[ZIP_CRYPTO_HEADER_SIZE - 1]
+    }
+}
+
+impl Decryptor for ZipCryptoKeys {
+    fn decrypt(&mut self, data: &mut [u8]) {
+        for value in data {
+            *value ^= self.stream_byte();
+            self.update(*value);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Streams the decompressed contents of a single entry.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crc32fast::Hasher;
+use tokio::io::{AsyncRead, ReadBuf, Take};
+
+use crate::{compression::Decoder, crypto::Decryptor, ArchiveFile, ArchiveLimits, Error};
+
+/// Amount of compressed data to read from the archive at a time.
+const INPUT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Reads the decompressed bytes of an entry.
+///
+/// The compressed data is read from the archive and decompressed as it's needed.
+pub struct EntryReader<'a> {
+    /// The compressed data which follows the Local File Header.
+    source: Take<&'a mut ArchiveFile>,
+    source_eof: bool,
+
+    /// Decrypts the compressed data as it's read.
+    decryptor: Option<Box<dyn Decryptor>>,
+
+    decoder: Box<dyn Decoder>,
+    finished: bool,
+
+    input: Vec<u8>,
+    input_pos: usize,
+    /// The decoder couldn't make progress with the input we have buffered.
+    needs_input: bool,
+
+    output: Vec<u8>,
+    output_pos: usize,
+
+    /// Amount of compressed data the decoder has used.
+    total_in: u64,
+    /// CRC-32 of the decompressed data we've output.
+    hasher: Hasher,
+    /// Amount of decompressed data we've output.
+    total_out: u64,
+    /// What the CRC-32 should be once we reach the end.
+    expected_crc_32: Option<u32>,
+    /// What the uncompressed size should be.
+    expected_size: Option<u64>,
+    verified: bool,
+
+    /// Checked as we decompress, since the headers can't be trusted.
+    limits: ArchiveLimits,
+    /// Amount other entries have decompressed to, which counts towards the total limit.
+    previous_total_out: u64,
+}
+
+impl<'a> EntryReader<'a> {
+    pub(crate) fn new(
+        source: Take<&'a mut ArchiveFile>,
+        decoder: Box<dyn Decoder>,
+        expected_crc_32: Option<u32>,
+        expected_size: Option<u64>,
+    ) -> Self {
+        Self {
+            source,
+            source_eof: false,
+
+            decryptor: None,
+
+            decoder,
+            finished: false,
+
+            input: Vec::with_capacity(INPUT_CHUNK_SIZE),
+            input_pos: 0,
+            needs_input: false,
+
+            output: Vec::new(),
+            output_pos: 0,
+
+            total_in: 0,
+            hasher: Hasher::new(),
+            total_out: 0,
+            expected_crc_32,
+            expected_size,
+            verified: false,
+
+            limits: ArchiveLimits::default(),
+            previous_total_out: 0,
+        }
+    }
+
+    /// Amount of compressed data used so far.
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    /// CRC-32 of the decompressed data read so far.
+    pub fn crc_32(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+
+    /// Amount of decompressed data read so far.
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub(crate) fn with_decryptor(mut self, decryptor: Box<dyn Decryptor>) -> Self {
+        self.decryptor = Some(decryptor);
+        self
+    }
+
+    /// Stop decompressing once we go over the `limits`. `previous_total_out` have already been decompressed.
+    pub(crate) fn with_limits(mut self, limits: ArchiveLimits, previous_total_out: u64) -> Self {
+        self.limits = limits;
+        self.previous_total_out = previous_total_out;
+        self
+    }
+
+    pub(crate) fn into_source(self) -> Take<&'a mut ArchiveFile> {
+        self.source
+    }
+
+    /// Read the next chunk of compressed data onto the end of our input buffer.
+    fn poll_fill_input(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Move any unused input to the front.
+        self.input.drain(..self.input_pos);
+        self.input_pos = 0;
+
+        let filled = self.input.len();
+        self.input.resize(filled + INPUT_CHUNK_SIZE, 0);
+
+        let mut buf = ReadBuf::new(&mut self.input[filled..]);
+
+        let res = Pin::new(&mut self.source).poll_read(cx, &mut buf);
+        let amount = buf.filled().len();
+
+        self.input.truncate(filled + amount);
+
+        if let Some(decryptor) = self.decryptor.as_mut() {
+            decryptor.decrypt(&mut self.input[filled..]);
+        }
+
+        if let Poll::Ready(Ok(())) = res {
+            self.source_eof = amount == 0;
+        }
+
+        res
+    }
+
+    /// Run the decoder over our buffered input.
+    fn decode(&mut self) -> io::Result<()> {
+        self.output.clear();
+        self.output_pos = 0;
+
+        let input = &self.input[self.input_pos..];
+        let eof = self.source_eof;
+
+        let decoded = self.decoder.decode(input, eof, &mut self.output)?;
+
+        self.input_pos += decoded.consumed;
+        self.total_in += decoded.consumed as u64;
+        self.finished = decoded.finished;
+
+        self.hasher.update(&self.output);
+        self.total_out += self.output.len() as u64;
+
+        // Stop early instead of decompressing more than we were told about.
+        if let Some(expected) = self.expected_size.filter(|v| self.total_out > *v) {
+            return Err(Error::SizeMismatch {
+                expected,
+                found: self.total_out,
+            }
+            .into());
+        }
+
+        self.limits
+            .check_total(self.previous_total_out.saturating_add(self.total_out))?;
+
+        // The output can run ahead of the input used so far, so allow for another chunk of input.
+        if let Some(limit) = self
+            .limits
+            .max_compression_ratio
+            .filter(|v| self.total_out > v.saturating_mul(self.total_in + INPUT_CHUNK_SIZE as u64))
+        {
+            return Err(Error::CompressionRatioExceeded {
+                limit,
+                compressed_size: self.total_in,
+                uncompressed_size: self.total_out,
+            }
+            .into());
+        }
+
+        if !self.finished && self.output.is_empty() && decoded.consumed == 0 {
+            if eof {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "compressed data ended before the end of the stream",
+                ));
+            }
+
+            self.needs_input = true;
+        }
+
+        Ok(())
+    }
+
+    /// Compare what we've output against what the headers told us.
+    fn verify(&mut self) -> Result<(), Error> {
+        self.verified = true;
+
+        if let Some(decryptor) = self.decryptor.as_mut() {
+            decryptor.finish()?;
+        }
+
+        if let Some(expected) = self.expected_size.filter(|v| *v != self.total_out) {
+            return Err(Error::SizeMismatch {
+                expected,
+                found: self.total_out,
+            });
+        }
+
+        let found = self.crc_32();
+
+        if let Some(expected) = self.expected_crc_32.filter(|v| *v != found) {
+            return Err(Error::ChecksumMismatch { expected, found });
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> AsyncRead for EntryReader<'a> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.output_pos < this.output.len() {
+                let amount = buf.remaining().min(this.output.len() - this.output_pos);
+
+                buf.put_slice(&this.output[this.output_pos..this.output_pos + amount]);
+                this.output_pos += amount;
+
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.finished {
+                if !this.verified {
+                    this.verify()?;
+                }
+
+                return Poll::Ready(Ok(()));
+            }
+
+            if (this.needs_input || this.input_pos == this.input.len()) && !this.source_eof {
+                match this.poll_fill_input(cx) {
+                    Poll::Ready(Ok(())) => this.needs_input = false,
+                    v => return v,
+                }
+            }
+
+            this.decode()?;
+        }
+    }
+}
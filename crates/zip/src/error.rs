@@ -1,3 +1,5 @@
+use std::io;
+
 use num_enum::TryFromPrimitiveError;
 use thiserror::Error as ThisError;
 
@@ -6,7 +8,7 @@ pub type Result<R, E = Error> = std::result::Result<R, E>;
 #[derive(Debug, ThisError)]
 pub enum Error {
     #[error("IO Error: {0:?}")]
-    Io(#[from] std::io::Error),
+    Io(io::Error),
 
     #[error("UTF-8 Error: {0:?}")]
     Utf8(#[from] std::string::FromUtf8Error),
@@ -17,6 +19,132 @@ pub enum Error {
     #[error("LZMA Error: {0:?}")]
     Lzma(#[from] lzma_rs::error::Error),
 
+    #[error("Unsupported Compression: {0:?}")]
+    UnsupportedCompression(crate::compression::CompressionType),
+
+    #[error("Checksum Mismatch: expected {expected:#010X}, found {found:#010X}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+
+    #[error("Size Mismatch: expected {expected}, found {found}")]
+    SizeMismatch { expected: u64, found: u64 },
+
+    #[error("Password Required")]
+    PasswordRequired,
+
+    #[error("Invalid Password")]
+    InvalidPassword,
+
+    #[error("Authentication Code Mismatch")]
+    AuthenticationMismatch,
+
+    #[error("Unsupported Encryption")]
+    UnsupportedEncryption,
+
+    #[error(
+        "Missing AES Extra Field: the entry is AES encrypted, but without the 0x9901 extra field"
+    )]
+    MissingAesExtraField,
+
+    #[error("Unknown entry size for {0:?} compressed data")]
+    UnknownEntrySize(crate::compression::CompressionType),
+
+    #[error("Missing Local Header")]
+    MissingLocalHeader,
+
+    #[error("Missing Data Descriptor")]
+    MissingDataDescriptor,
+
+    #[error("Missing Central Directory Header at {0}")]
+    MissingCentralDirHeader(u64),
+
+    #[error("Central Directory Overrun: record at {offset} runs past the end at {end}")]
+    CentralDirOverrun { offset: u64, end: u64 },
+
+    #[error("Entry Overrun: entry at {offset} runs past the end of the archive at {end}")]
+    EntryOverrun { offset: u64, end: u64 },
+
+    #[error("Central Directory Size Mismatch: expected {expected}, found {found}")]
+    CentralDirSizeMismatch { expected: u64, found: u64 },
+
+    #[error("Record Count Mismatch: expected {expected}, found {found}")]
+    RecordCountMismatch { expected: u64, found: u64 },
+
     #[error("Missing End Header")]
     MissingEndHeader,
+
+    #[error("Missing Zip64 End Header")]
+    MissingZip64EndHeader,
+
+    #[error("Zip64 End Header Overrun: {size} bytes of extensible data, but only {available} fit before the locator")]
+    Zip64EndHeaderOverrun { size: u64, available: u64 },
+
+    #[error("Missing Volume: disk {0} of the split archive wasn't found")]
+    MissingVolume(u32),
+
+    #[error("Volume Count Mismatch: expected {expected}, found {found}")]
+    VolumeCountMismatch { expected: u64, found: u64 },
+
+    #[error("Unsafe Path: {0:?} would be extracted outside of the destination")]
+    UnsafePath(String),
+
+    #[error("Already Exists: {0:?}")]
+    AlreadyExists(std::path::PathBuf),
+
+    #[error("Entry Not Found: {0:?}")]
+    EntryNotFound(String),
+
+    #[error("Entry Exists: {0:?} is already in the archive")]
+    EntryExists(String),
+
+    #[error("Too Many Entries: limit is {limit}, found {found}")]
+    TooManyEntries { limit: u64, found: u64 },
+
+    #[error("File Name Too Long: limit is {limit} bytes, found {found}")]
+    FileNameTooLong { limit: usize, found: usize },
+
+    #[error("Compression Ratio Exceeded: limit is {limit}, {compressed_size} bytes decompress to {uncompressed_size}")]
+    CompressionRatioExceeded {
+        limit: u64,
+        compressed_size: u64,
+        uncompressed_size: u64,
+    },
+
+    #[error("Uncompressed Size Exceeded: the entries decompress to more than {limit} bytes")]
+    UncompressedSizeExceeded { limit: u64 },
+
+    /// `found` is `None` when the decoder doesn't say how much it needs, like XZ.
+    #[error("Decoder Memory Exceeded: limit is {limit} bytes, the entry needs {found:?}")]
+    DecoderMemoryExceeded { limit: u64, found: Option<u64> },
+
+    #[error("Header Field Too Long: the {field} is {found} bytes, at most 65535 fit")]
+    HeaderFieldTooLong { field: &'static str, found: usize },
+
+    #[error("Overlapping Entries: {second:?} starts inside {first:?}")]
+    OverlappingEntries { first: String, second: String },
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        // Our errors get wrapped when they pass through an AsyncRead. Unwrap them back out.
+        if !value.get_ref().is_some_and(|v| v.is::<Self>()) {
+            return Self::Io(value);
+        }
+
+        let kind = value.kind();
+
+        match value.into_inner().map(|v| v.downcast::<Self>()) {
+            Some(Ok(error)) => *error,
+            Some(Err(inner)) => Self::Io(io::Error::new(kind, inner)),
+            None => Self::Io(kind.into()),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Io(error) => error,
+            error => io::Error::other(error),
+        }
+    }
 }
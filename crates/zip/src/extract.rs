@@ -0,0 +1,522 @@
+//! Extracts the entries of an archive to a directory.
+//!
+//! Entry names come from the archive so they can't be trusted. Names which could end up outside of the
+//! destination ("zip slip") are rejected before anything is written, and stop the whole extraction since the
+//! archive was made to do it. Symbolic links have to point inside the destination, and nothing is written through
+//! one.
+
+use std::{
+    collections::HashSet,
+    fs::FileTimes,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+
+use crate::{
+    Archive, ArchiveReader, CentralDirHeader, EntryKind, Error, ExtraField, LocalFileHeader, Result,
+};
+
+/// Extended timestamp flag for the access time.
+const EXTENDED_TIMESTAMP_ACCESSED: u8 = 1 << 1;
+
+/// Longest symbolic link target, the same as Linux's PATH_MAX.
+const MAX_LINK_TARGET_SIZE: u64 = 4096;
+
+/// Permission bits which are applied. Setuid, setgid and sticky aren't.
+const APPLIED_PERMISSIONS_MASK: u32 = 0o777;
+
+/// What to do when a file being extracted already exists.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Leave the existing file alone and move onto the next entry.
+    Skip,
+    /// Replace the existing file.
+    Overwrite,
+    /// Write to a new name instead. e.g. "File (1).txt"
+    Rename,
+    /// Stop with [`Error::AlreadyExists`].
+    #[default]
+    Error,
+}
+
+/// How an archive is extracted.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractOptions {
+    pub overwrite: OverwritePolicy,
+    /// Set the modification and access times of the files and folders written. Defaults to true.
+    pub set_times: bool,
+    /// Seconds east of UTC to read the MS-DOS times in, since they have no time zone. e.g. 3600 for UTC+1
+    ///
+    /// The extra field timestamps are always UTC so they don't use it.
+    pub utc_offset: i32,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            overwrite: OverwritePolicy::default(),
+            set_times: true,
+            utc_offset: 0,
+        }
+    }
+}
+
+impl Archive {
+    /// Extract every entry into `dir`, creating it if needed.
+    ///
+    /// Every name is checked before anything is written. Names which are only the destination itself, like "./",
+    /// are skipped. Symbolic links are recreated, and Unix permissions and timestamps are applied. Returns the paths
+    /// of the files, folders and links written.
+    pub async fn extract_to(
+        &mut self,
+        dir: impl AsRef<Path>,
+        options: ExtractOptions,
+    ) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+
+        let files = self.list_files().await?;
+
+        let mut entries = Vec::with_capacity(files.len());
+
+        for file in &files {
+            if let Some(path) = sanitize_name(&file.file_name)? {
+                entries.push((file, path));
+            }
+        }
+
+        // A link can be extracted before one it goes through, so the targets are checked against every link entry.
+        let links = entries
+            .iter()
+            .filter(|(file, _)| file.kind() == EntryKind::Symlink)
+            .map(|(_, path)| path.clone())
+            .collect::<HashSet<_>>();
+
+        fs::create_dir_all(dir).await?;
+
+        let mut written = Vec::new();
+
+        // Applied at the end so read-only folders can still be written to, and their times aren't changed by it.
+        let mut folders = Vec::new();
+
+        for (file, relative) in entries {
+            let path = dir.join(&relative);
+
+            // A link could have been extracted, or already be there, pointing anywhere.
+            if has_symlink_parent(dir, &relative).await? {
+                return Err(Error::UnsafePath(file.file_name.clone()));
+            }
+
+            match file.kind() {
+                EntryKind::Directory => {
+                    // Creating it, and setting its permissions and times, would follow the link.
+                    if is_symlink(&path).await {
+                        return Err(Error::UnsafePath(file.file_name.clone()));
+                    }
+
+                    fs::create_dir_all(&path).await?;
+
+                    let times = self.file_times(file, &options).await?;
+
+                    folders.push((path.clone(), file.unix_permissions(), times));
+                    written.push(path);
+                }
+
+                EntryKind::Symlink => {
+                    if file.uncompressed_size > MAX_LINK_TARGET_SIZE {
+                        return Err(Error::UnsafePath(file.file_name.clone()));
+                    }
+
+                    // The size in the header can't be trusted either. One more byte is read so the reader reaches
+                    // the end and checks it.
+                    let mut target = Vec::new();
+
+                    file.reader(self)
+                        .await?
+                        .take(MAX_LINK_TARGET_SIZE + 1)
+                        .read_to_end(&mut target)
+                        .await?;
+
+                    if target.len() as u64 > MAX_LINK_TARGET_SIZE {
+                        return Err(Error::UnsafePath(file.file_name.clone()));
+                    }
+
+                    let target = String::from_utf8(target)?;
+
+                    if !is_contained_link(&relative, &target)
+                        || has_symlink_target(dir, &relative, &target, &links).await?
+                    {
+                        return Err(Error::UnsafePath(file.file_name.clone()));
+                    }
+
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).await?;
+                    }
+
+                    let Some(path) = link_path(path, options.overwrite).await? else {
+                        continue;
+                    };
+
+                    create_symlink(&target, &path).await?;
+
+                    written.push(path);
+                }
+
+                EntryKind::File => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).await?;
+                    }
+
+                    let Some((mut output, path)) = create_file(path, options.overwrite).await?
+                    else {
+                        continue;
+                    };
+
+                    let result = async {
+                        tokio::io::copy(&mut file.reader(self).await?, &mut output).await?;
+                        output.flush().await?;
+
+                        Result::<_, Error>::Ok(())
+                    }
+                    .await;
+
+                    // Don't leave a partially written file behind.
+                    if let Err(e) = result {
+                        drop(output);
+                        let _ = fs::remove_file(&path).await;
+
+                        return Err(e);
+                    }
+
+                    if let Some(times) = self.file_times(file, &options).await? {
+                        output.into_std().await.set_times(times)?;
+                    }
+
+                    if let Some(permissions) = file.unix_permissions() {
+                        set_permissions(&path, permissions).await?;
+                    }
+
+                    written.push(path);
+                }
+            }
+        }
+
+        // Nested folders first.
+        for (path, permissions, times) in folders.into_iter().rev() {
+            if let Some(times) = times {
+                set_folder_times(&path, times).await?;
+            }
+
+            if let Some(permissions) = permissions {
+                set_permissions(&path, permissions).await?;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// The times to set on an extracted entry, if they're being set.
+    ///
+    /// The extended timestamp only has the access time in the Local File Header. Its flags in the Central Directory
+    /// say whether it's there, so the Local File Header is only read when it is. A header which can't be read just
+    /// means there's no access time, since the entry itself has already been read.
+    async fn file_times(
+        &mut self,
+        file: &CentralDirHeader,
+        options: &ExtractOptions,
+    ) -> Result<Option<FileTimes>> {
+        if !options.set_times {
+            return Ok(None);
+        }
+
+        let mut times =
+            FileTimes::new().set_modified(file.last_modified().to_system_time(options.utc_offset));
+
+        let local_accessed = file.extra_field.iter().any(|v| {
+            matches!(v, ExtraField::ExtendedTimestamp(v) if v.flags & EXTENDED_TIMESTAMP_ACCESSED != 0)
+        });
+
+        let accessed = match file.last_accessed() {
+            Some(v) => Some(v),
+            None if local_accessed => self.local_last_accessed(file).await.ok().flatten(),
+            None => None,
+        };
+
+        if let Some(accessed) = accessed {
+            times = times.set_accessed(accessed);
+        }
+
+        Ok(Some(times))
+    }
+
+    async fn local_last_accessed(&mut self, file: &CentralDirHeader) -> Result<Option<SystemTime>> {
+        let offset = file.local_header_position(&self.file)?;
+        let mut reader = ArchiveReader::init(&mut self.file).await?;
+
+        let local_header =
+            LocalFileHeader::parse(&mut reader, offset, self.options.fallback_encoding).await?;
+
+        Ok(local_header.last_accessed())
+    }
+}
+
+/// Turn an entry name into a path relative to the destination.
+///
+/// Absolute paths, drive letters and `..` components are [`Error::UnsafePath`]. `.` components are ignored, so
+/// `None` is returned for a name which is only the destination. Backslashes are treated as separators since some
+/// archivers write them, and Windows would otherwise.
+pub(crate) fn sanitize_name(name: &str) -> Result<Option<PathBuf>> {
+    let unsafe_path = || Error::UnsafePath(name.to_string());
+
+    if name.starts_with(['/', '\\']) || name.contains('\0') || has_drive_prefix(name) {
+        return Err(unsafe_path());
+    }
+
+    let mut path = PathBuf::new();
+
+    for component in name.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => return Err(unsafe_path()),
+            // Drive letters, and NTFS alternate data streams. Anywhere else it's a normal character.
+            v if cfg!(windows) && v.contains(':') => return Err(unsafe_path()),
+            v => path.push(v),
+        }
+    }
+
+    Ok((path.components().next().is_some()).then_some(path))
+}
+
+/// Does the name start with a drive letter, like "C:". Windows would read it as a drive even without a separator.
+fn has_drive_prefix(name: &str) -> bool {
+    matches!(name.as_bytes(), [letter, b':', ..] if letter.is_ascii_alphabetic())
+}
+
+/// Is any folder `path` would be written in a symbolic link.
+async fn has_symlink_parent(dir: &Path, path: &Path) -> Result<bool> {
+    let mut current = dir.to_path_buf();
+
+    for component in path.parent().into_iter().flat_map(Path::components) {
+        current.push(component);
+
+        match fs::symlink_metadata(&current).await {
+            Ok(metadata) if metadata.is_symlink() => return Ok(true),
+            Ok(_) => {}
+            // Nothing further down exists yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(false)
+}
+
+/// Does a link at `path`, relative to the destination, to `target` go through a link which is already there, or
+/// one of the archive's `links` which could be extracted later.
+///
+/// Where that link points isn't known from the names alone, so the target can't be checked.
+async fn has_symlink_target(
+    dir: &Path,
+    path: &Path,
+    target: &str,
+    links: &HashSet<PathBuf>,
+) -> Result<bool> {
+    let mut current = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    for component in target.split(['/', '\\']) {
+        match component {
+            "" | "." => continue,
+            ".." => {
+                current.pop();
+                continue;
+            }
+            v => current.push(v),
+        }
+
+        if links.contains(&current) {
+            return Ok(true);
+        }
+
+        match fs::symlink_metadata(dir.join(&current)).await {
+            Ok(metadata) if metadata.is_symlink() => return Ok(true),
+            Ok(_) => {}
+            // A later ".." can come back to somewhere which does exist.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(false)
+}
+
+/// Does a link at `path`, relative to the destination, to `target` stay inside the destination.
+fn is_contained_link(path: &Path, target: &str) -> bool {
+    if target.starts_with(['/', '\\']) || target.contains([':', '\0']) {
+        return false;
+    }
+
+    // Links are relative to the folder they're in.
+    let mut depth = path.components().count().saturating_sub(1);
+
+    for component in target.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => match depth.checked_sub(1) {
+                Some(v) => depth = v,
+                None => return false,
+            },
+            _ => depth += 1,
+        }
+    }
+
+    true
+}
+
+/// Create the file for an entry following the overwrite policy.
+///
+/// Returns `None` when the entry should be skipped, otherwise the file and the path it was created at.
+async fn create_file(path: PathBuf, policy: OverwritePolicy) -> Result<Option<(File, PathBuf)>> {
+    if policy == OverwritePolicy::Overwrite {
+        // Replace a link rather than writing to what it points to.
+        if is_symlink(&path).await {
+            fs::remove_file(&path).await?;
+        }
+
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .await?;
+
+        return Ok(Some((file, path)));
+    }
+
+    if let Some(file) = create_new(&path).await? {
+        return Ok(Some((file, path)));
+    }
+
+    match policy {
+        OverwritePolicy::Rename => {
+            for number in 1.. {
+                let renamed = numbered_path(&path, number);
+
+                if let Some(file) = create_new(&renamed).await? {
+                    return Ok(Some((file, renamed)));
+                }
+            }
+
+            Err(Error::AlreadyExists(path))
+        }
+
+        OverwritePolicy::Skip => Ok(None),
+
+        _ => Err(Error::AlreadyExists(path)),
+    }
+}
+
+/// Find where a symbolic link can be created following the overwrite policy.
+///
+/// Returns `None` when the entry should be skipped.
+async fn link_path(path: PathBuf, policy: OverwritePolicy) -> Result<Option<PathBuf>> {
+    if fs::symlink_metadata(&path).await.is_err() {
+        return Ok(Some(path));
+    }
+
+    match policy {
+        OverwritePolicy::Overwrite => {
+            fs::remove_file(&path).await?;
+
+            Ok(Some(path))
+        }
+
+        OverwritePolicy::Rename => {
+            for number in 1.. {
+                let renamed = numbered_path(&path, number);
+
+                if fs::symlink_metadata(&renamed).await.is_err() {
+                    return Ok(Some(renamed));
+                }
+            }
+
+            Err(Error::AlreadyExists(path))
+        }
+
+        OverwritePolicy::Skip => Ok(None),
+
+        OverwritePolicy::Error => Err(Error::AlreadyExists(path)),
+    }
+}
+
+async fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .await
+        .is_ok_and(|v| v.is_symlink())
+}
+
+#[cfg(unix)]
+async fn create_symlink(target: &str, path: &Path) -> Result<()> {
+    Ok(fs::symlink(target, path).await?)
+}
+
+/// Without symbolic links the target is written as the contents, like other archivers do.
+#[cfg(not(unix))]
+async fn create_symlink(target: &str, path: &Path) -> Result<()> {
+    Ok(fs::write(path, target).await?)
+}
+
+/// Folders can only be opened to set their times on Unix.
+#[cfg(unix)]
+async fn set_folder_times(path: &Path, times: FileTimes) -> Result<()> {
+    Ok(File::open(path).await?.into_std().await.set_times(times)?)
+}
+
+#[cfg(not(unix))]
+async fn set_folder_times(_path: &Path, _times: FileTimes) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn set_permissions(path: &Path, permissions: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let permissions = std::fs::Permissions::from_mode(permissions & APPLIED_PERMISSIONS_MASK);
+
+    Ok(fs::set_permissions(path, permissions).await?)
+}
+
+#[cfg(not(unix))]
+async fn set_permissions(_path: &Path, _permissions: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Create a file which doesn't exist yet. Returns `None` if it does.
+async fn create_new(path: &Path) -> Result<Option<File>> {
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await
+    {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// "File.txt" to "File (1).txt"
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let name = match path.extension() {
+        Some(extension) => format!("{stem} ({number}).{}", extension.to_string_lossy()),
+        None => format!("{stem} ({number})"),
+    };
+
+    path.with_file_name(name)
+}
@@ -1,12 +1,27 @@
-use std::{fmt, io::SeekFrom};
+use std::{fmt, io::SeekFrom, time::SystemTime};
 
+use encoding_rs::Encoding;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 use crate::{
-    compression::CompressionType, Archive, ArchiveReader, Result, BUFFER_SIZE, SIGNATURE_SIZE,
+    compression::CompressionType,
+    crypto::{
+        AesDecryptor, AesStrength, Decryptor, ZipCryptoKeys, AES_AUTHENTICATION_CODE_SIZE,
+        AES_PASSWORD_VERIFIER_SIZE, ZIP_CRYPTO_HEADER_SIZE,
+    },
+    limits::check_overlap,
+    text::decode_text,
+    time::extra_field_times,
+    Archive, ArchiveFile, ArchiveLimits, ArchiveOptions, ArchiveReader, DosDateTime, EntryReader,
+    EntryTime, Error, Result, BUFFER_SIZE, DATA_DESCRIPTOR_MAX_SIZE, SIGNATURE_SIZE,
 };
 
-use super::LocalFileHeader;
+use super::{
+    AesExtraField, DataDescriptor, EndCentralDirHeader, ExtraField, LocalFileHeader, Zip64Fields,
+    DOS_DIRECTORY_ATTR, GP_FLAG_DATA_DESCRIPTOR, GP_FLAG_ENCRYPTED, GP_FLAG_STRONG_ENCRYPTION,
+    GP_FLAG_UTF8, HOST_OSX, HOST_UNIX, UNIX_DIRECTORY, UNIX_FILE_TYPE_MASK, UNIX_PERMISSIONS_MASK,
+    UNIX_SYMLINK,
+};
 
 pub(crate) const CENTRAL_DIR_SIG: [u8; 4] = [0x50, 0x4B, 0x01, 0x02];
 pub(crate) const CENTRAL_DIR_SIZE_KNOWN: usize = 46;
@@ -28,41 +43,47 @@ pub struct CentralDirHeader {
     pub file_last_mod_date: u16,
     /// CRC-32 of uncompressed data
     pub crc_32: u32,
-    /// Compressed size (or 0xffffffff for ZIP64)
-    pub compressed_size: u32,
-    /// Uncompressed size (or 0xffffffff for ZIP64)
-    pub uncompressed_size: u32,
+    /// Compressed size (read from the Zip64 extra field when the header holds 0xffffffff)
+    pub compressed_size: u64,
+    /// Uncompressed size (read from the Zip64 extra field when the header holds 0xffffffff)
+    pub uncompressed_size: u64,
     /// File name length (n)
     pub file_name_length: u16,
     /// Extra field length (m)
     pub extra_field_length: u16,
     /// File comment length (k)
     pub file_comment_length: u16,
-    /// Disk number where file starts (or 0xffff for ZIP64)
-    pub current_disk_number: u16,
+    /// Disk number where file starts (read from the Zip64 extra field when the header holds 0xffff)
+    pub current_disk_number: u32,
     /// Internal file attributes
     pub internal_file_attr: u16,
     /// External file attributes
     pub external_file_attr: u32,
-    /// Relative offset of local file header (or 0xffffffff for ZIP64). This is the number of bytes between the start of the first disk on which the file occurs, and the start of the local file header. This allows software reading the central directory to locate the position of the file inside the ZIP file.
-    pub relative_offset: u32,
-    /// File name
+    /// Relative offset of local file header (read from the Zip64 extra field when the header holds 0xffffffff). This is the number of bytes between the start of the first disk on which the file occurs, and the start of the local file header. This allows software reading the central directory to locate the position of the file inside the ZIP file.
+    pub relative_offset: u64,
+    /// File name. Decoded from UTF-8 when general purpose bit 11 is set, otherwise CP437 or the fallback encoding.
+    /// The Info-ZIP Unicode Path extra field is preferred when it matches.
     pub file_name: String,
+    /// File name bytes as they're stored in the header
+    pub file_name_raw: Vec<u8>,
     /// Used to store additional information.
     ///
     /// The field consists of a sequence of header and data pairs, where the header has a 2 byte identifier and a 2 byte data size field.
-    pub extra_field: Vec<(u16, u16)>,
-    /// File comment
+    pub extra_field: Vec<ExtraField>,
+    /// Extra field bytes as they're stored in the header
+    pub extra_field_raw: Vec<u8>,
+    /// File comment. Decoded the same way as the file name.
     pub file_comment: String,
+    /// File comment bytes as they're stored in the header
+    pub file_comment_raw: Vec<u8>,
 }
 
 impl CentralDirHeader {
     pub async fn parse(
         reader: &mut ArchiveReader<'_>,
         buffer: &mut [u8; BUFFER_SIZE],
+        fallback_encoding: Option<&'static Encoding>,
     ) -> Result<Self> {
-        assert_eq!(&buffer[reader.index..reader.index + 4], &CENTRAL_DIR_SIG);
-
         reader.skip::<4>();
 
         let mut header = Self {
@@ -73,83 +94,510 @@ impl CentralDirHeader {
             file_last_mod_time: reader.next_u16(buffer).await?,
             file_last_mod_date: reader.next_u16(buffer).await?,
             crc_32: reader.next_u32(buffer).await?,
-            compressed_size: reader.next_u32(buffer).await?,
-            uncompressed_size: reader.next_u32(buffer).await?,
+            compressed_size: reader.next_u32(buffer).await?.into(),
+            uncompressed_size: reader.next_u32(buffer).await?.into(),
             file_name_length: reader.next_u16(buffer).await?,
             extra_field_length: reader.next_u16(buffer).await?,
             file_comment_length: reader.next_u16(buffer).await?,
-            current_disk_number: reader.next_u16(buffer).await?,
+            current_disk_number: reader.next_u16(buffer).await?.into(),
             internal_file_attr: reader.next_u16(buffer).await?,
             external_file_attr: reader.next_u32(buffer).await?,
-            relative_offset: reader.next_u32(buffer).await?,
+            relative_offset: reader.next_u32(buffer).await?.into(),
             file_name: String::new(),
+            file_name_raw: Vec::new(),
             extra_field: Vec::new(),
+            extra_field_raw: Vec::new(),
             file_comment: String::new(),
+            file_comment_raw: Vec::new(),
         };
 
-        header.file_name = String::from_utf8(
-            reader
-                .get_chunk_amount(buffer, header.file_name_length as usize)
-                .await?,
-        )?;
-        header.extra_field = reader
+        header.file_name_raw = reader
+            .get_chunk_amount(buffer, header.file_name_length as usize)
+            .await?;
+
+        header.extra_field_raw = reader
             .get_chunk_amount(buffer, header.extra_field_length as usize)
-            .await?
-            .into_iter()
-            .array_chunks::<4>()
-            .map(|v| {
-                (
-                    (u16::from(v[0]) << 8) | u16::from(v[1]),
-                    (u16::from(v[2]) << 8) | u16::from(v[3]),
-                )
-            })
-            .collect();
-        header.file_comment = String::from_utf8(
-            reader
-                .get_chunk_amount(buffer, header.file_comment_length as usize)
-                .await?,
-        )?;
+            .await?;
+
+        header.extra_field = ExtraField::parse_all(
+            &header.extra_field_raw,
+            Zip64Fields {
+                uncompressed_size: header.uncompressed_size == u32::MAX as u64,
+                compressed_size: header.compressed_size == u32::MAX as u64,
+                relative_offset: header.relative_offset == u32::MAX as u64,
+                disk_start_number: header.current_disk_number == u16::MAX as u32,
+            },
+        );
+
+        header.apply_zip64();
+
+        header.file_comment_raw = reader
+            .get_chunk_amount(buffer, header.file_comment_length as usize)
+            .await?;
+
+        header.decode_text(fallback_encoding);
 
         Ok(header)
     }
 
-    pub async fn read(&self, archive: &mut Archive) -> Result<String> {
+    fn decode_text(&mut self, fallback_encoding: Option<&'static Encoding>) {
+        let is_utf8 = self.gp_flag & GP_FLAG_UTF8 != 0;
+
+        let unicode_path = self.extra_field.iter().find_map(|v| match v {
+            ExtraField::UnicodePath(v) => Some(v),
+            _ => None,
+        });
+
+        let unicode_comment = self.extra_field.iter().find_map(|v| match v {
+            ExtraField::UnicodeComment(v) => Some(v),
+            _ => None,
+        });
+
+        self.file_name = decode_text(
+            &self.file_name_raw,
+            is_utf8,
+            unicode_path,
+            fallback_encoding,
+        );
+        self.file_comment = decode_text(
+            &self.file_comment_raw,
+            is_utf8,
+            unicode_comment,
+            fallback_encoding,
+        );
+    }
+
+    /// Replace the saturated (-1) fields with the values from the Zip64 extended information extra field.
+    fn apply_zip64(&mut self) {
+        let Some(zip64) = self.extra_field.iter().find_map(|v| match v {
+            ExtraField::Zip64(v) => Some(*v),
+            _ => None,
+        }) else {
+            return;
+        };
+
+        self.uncompressed_size = zip64.uncompressed_size.unwrap_or(self.uncompressed_size);
+        self.compressed_size = zip64.compressed_size.unwrap_or(self.compressed_size);
+        self.relative_offset = zip64.relative_offset.unwrap_or(self.relative_offset);
+        self.current_disk_number = zip64.disk_start_number.unwrap_or(self.current_disk_number);
+    }
+
+    /// Stand in for a missing record using the Local File Header at `relative_offset` on the first disk.
+    ///
+    /// The local header doesn't have the host system, attributes or comment so they're left empty.
+    pub(crate) fn from_local_header(header: &LocalFileHeader, relative_offset: u64) -> Self {
+        Self {
+            by_version: Version::from_bytes(0, header.min_version as u8),
+            min_version: VersionNeeded(header.min_version),
+            gp_flag: header.gp_flag,
+            compression: header.compression,
+            file_last_mod_time: header.file_last_mod_time,
+            file_last_mod_date: header.file_last_mod_date,
+            crc_32: header.crc_32,
+            compressed_size: header.compressed_size,
+            uncompressed_size: header.uncompressed_size,
+            file_name_length: header.file_name_length,
+            extra_field_length: header.extra_field_length,
+            file_comment_length: 0,
+            current_disk_number: 0,
+            internal_file_attr: 0,
+            external_file_attr: 0,
+            relative_offset,
+            file_name: header.file_name.clone(),
+            file_name_raw: header.file_name_raw.clone(),
+            extra_field: header.extra_field.clone(),
+            extra_field_raw: header.extra_field_raw.clone(),
+            file_comment: String::new(),
+            file_comment_raw: Vec::new(),
+        }
+    }
+
+    /// Size of the record, including the variable length fields.
+    pub fn size(&self) -> u64 {
+        (CENTRAL_DIR_SIZE_KNOWN
+            + self.file_name_length as usize
+            + self.extra_field_length as usize
+            + self.file_comment_length as usize) as u64
+    }
+
+    /// WinZip AES encryption details from the 0x9901 extra field.
+    pub fn aes(&self) -> Option<AesExtraField> {
+        self.extra_field.iter().find_map(|v| match v {
+            ExtraField::Aes(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.gp_flag & GP_FLAG_ENCRYPTED != 0
+    }
+
+    /// Are the CRC-32 and sizes also in a data descriptor after the compressed data.
+    pub fn has_data_descriptor(&self) -> bool {
+        self.gp_flag & GP_FLAG_DATA_DESCRIPTOR != 0
+    }
+
+    /// The MS-DOS last modification date and time fields.
+    pub fn dos_last_modified(&self) -> DosDateTime {
+        DosDateTime::from_fields(self.file_last_mod_time, self.file_last_mod_date)
+    }
+
+    /// The last modification time. The NTFS or extended timestamp extra fields are used when present,
+    /// otherwise it's the MS-DOS date and time.
+    pub fn last_modified(&self) -> EntryTime {
+        match extra_field_times(&self.extra_field).0 {
+            Some(v) => EntryTime::Utc(v),
+            None => EntryTime::Local(self.dos_last_modified()),
+        }
+    }
+
+    /// The last access time from the NTFS extra field. The extended timestamp only has it in the Local File Header.
+    pub fn last_accessed(&self) -> Option<SystemTime> {
+        extra_field_times(&self.extra_field).1
+    }
+
+    /// The Unix mode from the upper 16 bits of the external file attributes. Only set when made on Unix.
+    pub fn unix_mode(&self) -> Option<u32> {
+        let mode = self.external_file_attr >> 16;
+
+        (self.by_version.is_unix() && mode != 0).then_some(mode)
+    }
+
+    /// The Unix permission bits of the mode, including setuid, setgid and sticky.
+    pub fn unix_permissions(&self) -> Option<u32> {
+        self.unix_mode().map(|v| v & UNIX_PERMISSIONS_MASK)
+    }
+
+    /// Whether the entry is a file, folder or symbolic link.
+    ///
+    /// The Unix file type is used when there is one, then the trailing slash and the MS-DOS directory attribute.
+    pub fn kind(&self) -> EntryKind {
+        match self.unix_mode().map(|v| v & UNIX_FILE_TYPE_MASK) {
+            Some(UNIX_SYMLINK) => EntryKind::Symlink,
+            Some(UNIX_DIRECTORY) => EntryKind::Directory,
+            _ if self.file_name.ends_with(['/', '\\']) => EntryKind::Directory,
+            _ if self.external_file_attr & DOS_DIRECTORY_ATTR != 0 => EntryKind::Directory,
+            _ => EntryKind::File,
+        }
+    }
+
+    /// Stream the decompressed contents of the file.
+    pub async fn reader<'a>(&self, archive: &'a mut Archive) -> Result<EntryReader<'a>> {
+        self.open_reader(archive, None).await
+    }
+
+    /// Stream the decompressed contents of an encrypted file.
+    pub async fn reader_with_password<'a>(
+        &self,
+        archive: &'a mut Archive,
+        password: impl AsRef<[u8]>,
+    ) -> Result<EntryReader<'a>> {
+        self.open_reader(archive, Some(password.as_ref())).await
+    }
+
+    /// Read the decompressed contents of the file.
+    pub async fn read(&self, archive: &mut Archive) -> Result<Vec<u8>> {
+        let mut contents = Vec::new();
+
+        self.reader(archive)
+            .await?
+            .read_to_end(&mut contents)
+            .await?;
+
+        Ok(contents)
+    }
+
+    /// Read the decompressed contents of an encrypted file.
+    pub async fn read_with_password(
+        &self,
+        archive: &mut Archive,
+        password: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>> {
+        let mut contents = Vec::new();
+
+        self.reader_with_password(archive, password)
+            .await?
+            .read_to_end(&mut contents)
+            .await?;
+
+        Ok(contents)
+    }
+
+    /// Where the Local File Header is in the volume set. The relative offset is from the start of its disk.
+    pub(crate) fn local_header_position(&self, file: &ArchiveFile) -> Result<u64> {
+        file.disk_position(self.current_disk_number, self.relative_offset)
+    }
+
+    /// Where the entry is stored, from the start of its Local File Header to the end of its data descriptor.
+    pub(crate) async fn extent(&self, archive: &mut Archive) -> Result<EntryExtent> {
+        let offset = self.local_header_position(&archive.file)?;
+
         let mut reader = ArchiveReader::init(&mut archive.file).await?;
+        let local_header =
+            LocalFileHeader::parse(&mut reader, offset, archive.options.fallback_encoding).await?;
+
+        // The sizes can come from a Zip64 field, so they're checked against the archive rather than trusted.
+        let archive_end = archive.file.seek(SeekFrom::End(0)).await?;
+        let data_end = offset
+            .checked_add(local_header.size())
+            .and_then(|v| v.checked_add(self.compressed_size))
+            .filter(|v| *v <= archive_end)
+            .ok_or(Error::EntryOverrun {
+                offset,
+                end: archive_end,
+            })?;
+        let mut size = data_end - offset;
+        let mut data_descriptor = None;
+
+        if self.has_data_descriptor() {
+            let mut bytes = Vec::with_capacity(DATA_DESCRIPTOR_MAX_SIZE);
+
+            archive.file.seek(SeekFrom::Start(data_end)).await?;
+            (&mut archive.file)
+                .take(DATA_DESCRIPTOR_MAX_SIZE as u64)
+                .read_to_end(&mut bytes)
+                .await?;
+
+            let (descriptor, descriptor_size) =
+                DataDescriptor::from_bytes(&bytes, local_header.is_zip64, self.compressed_size)
+                    .ok_or(Error::MissingDataDescriptor)?;
 
-        Ok(
-            LocalFileHeader::parse(&mut reader, self.relative_offset as u64)
-                .await?
-                .1,
-        )
+            size += descriptor_size as u64;
+            data_descriptor = Some(descriptor);
+        }
+
+        Ok(EntryExtent {
+            offset,
+            size,
+            local_header,
+            data_descriptor,
+        })
+    }
+
+    async fn open_reader<'a>(
+        &self,
+        archive: &'a mut Archive,
+        password: Option<&[u8]>,
+    ) -> Result<EntryReader<'a>> {
+        let offset = self.local_header_position(&archive.file)?;
+
+        let mut reader = ArchiveReader::init(&mut archive.file).await?;
+
+        let local_header =
+            LocalFileHeader::parse(&mut reader, offset, archive.options.fallback_encoding).await?;
+
+        // The Central Directory sizes are used since the Local Header ones can be zero.
+        archive
+            .file
+            .seek(SeekFrom::Start(offset + local_header.size()))
+            .await?;
+
+        let mut compression = self.compression;
+        let mut expected_crc_32 = Some(self.crc_32);
+        let mut compressed_size = self.compressed_size;
+        let mut decryptor = None;
+
+        if self.is_encrypted() {
+            let decryption = self.open_decryptor(&mut archive.file, password).await?;
+
+            compression = decryption.compression;
+            compressed_size = decryption.size;
+            expected_crc_32 = expected_crc_32.filter(|_| decryption.has_crc_32);
+            decryptor = Some(decryption.decryptor);
+        }
+
+        let decoder = compression.decoder(
+            self.gp_flag,
+            Some(self.uncompressed_size),
+            archive.options.limits,
+        )?;
+
+        let reader = EntryReader::new(
+            (&mut archive.file).take(compressed_size),
+            decoder,
+            expected_crc_32,
+            Some(self.uncompressed_size),
+        );
+
+        Ok(match decryptor {
+            Some(value) => reader.with_decryptor(value),
+            None => reader,
+        })
+    }
+
+    /// Read the encryption header of an encrypted entry, which the file has to be at the start of.
+    pub(crate) async fn open_decryptor(
+        &self,
+        file: &mut ArchiveFile,
+        password: Option<&[u8]>,
+    ) -> Result<Decryption> {
+        if self.gp_flag & GP_FLAG_STRONG_ENCRYPTION != 0 {
+            return Err(Error::UnsupportedEncryption);
+        }
+
+        // Method 99 only says the data is AES encrypted. The rest is in the extra field.
+        let aes = match self.compression {
+            CompressionType::Aex => Some(self.aes().ok_or(Error::MissingAesExtraField)?),
+            _ => None,
+        };
+
+        let password = password.ok_or(Error::PasswordRequired)?;
+
+        let (decryptor, size) = match aes {
+            Some(aes) => self.open_aes(file, aes, password).await?,
+            None => self.open_zip_crypto(file, password).await?,
+        };
+
+        Ok(Decryption {
+            decryptor,
+            compression: aes.map_or(self.compression, |v| v.compression),
+            size,
+            has_crc_32: aes.is_none_or(|v| v.has_crc_32()),
+        })
+    }
+
+    /// Read the encryption header. Returns the decryptor and the size of the encrypted data which follows.
+    async fn open_zip_crypto(
+        &self,
+        file: &mut ArchiveFile,
+        password: &[u8],
+    ) -> Result<(Box<dyn Decryptor>, u64)> {
+        let mut header = [0u8; ZIP_CRYPTO_HEADER_SIZE];
+        file.read_exact(&mut header).await?;
+
+        let mut keys = ZipCryptoKeys::new(password);
+
+        let check = if self.has_data_descriptor() {
+            (self.file_last_mod_time >> 8) as u8
+        } else {
+            (self.crc_32 >> 24) as u8
+        };
+
+        if keys.decrypt_header(header) != check {
+            return Err(Error::InvalidPassword);
+        }
+
+        Ok((
+            Box::new(keys),
+            self.compressed_size
+                .saturating_sub(ZIP_CRYPTO_HEADER_SIZE as u64),
+        ))
+    }
+
+    /// Read the salt, password verification value and authentication code.
+    /// Returns the decryptor and the size of the encrypted data which follows.
+    async fn open_aes(
+        &self,
+        file: &mut ArchiveFile,
+        aes: AesExtraField,
+        password: &[u8],
+    ) -> Result<(Box<dyn Decryptor>, u64)> {
+        let strength = AesStrength::from_u8(aes.strength).ok_or(Error::UnsupportedEncryption)?;
+
+        let mut salt = vec![0u8; strength.salt_size()];
+        file.read_exact(&mut salt).await?;
+
+        let mut verifier = [0u8; AES_PASSWORD_VERIFIER_SIZE];
+        file.read_exact(&mut verifier).await?;
+
+        let data_size = self.compressed_size.saturating_sub(
+            (salt.len() + AES_PASSWORD_VERIFIER_SIZE + AES_AUTHENTICATION_CODE_SIZE) as u64,
+        );
+
+        // The authentication code follows the encrypted data.
+        let data_start = file.stream_position().await?;
+
+        file.seek(SeekFrom::Start(data_start + data_size)).await?;
+
+        let mut authentication_code = [0u8; AES_AUTHENTICATION_CODE_SIZE];
+        file.read_exact(&mut authentication_code).await?;
+
+        file.seek(SeekFrom::Start(data_start)).await?;
+
+        let decryptor = AesDecryptor::new(password, strength, &salt, verifier, authentication_code)
+            .ok_or(Error::InvalidPassword)?;
+
+        Ok((Box::new(decryptor), data_size))
     }
 }
 
-// Used so we don't have to have load all the files on initial open.
+/// An encrypted entry once its encryption header has been read.
+pub(crate) struct Decryption {
+    pub decryptor: Box<dyn Decryptor>,
+    /// How the data was compressed before it was encrypted.
+    pub compression: CompressionType,
+    /// Size of the encrypted data which follows.
+    pub size: u64,
+    /// AE-2 doesn't store the CRC-32.
+    pub has_crc_32: bool,
+}
+
+// Used so we don't have to have load all the files on initial open. Records are read in order from the start of the Central Directory.
 #[derive(Default)]
 pub struct FileReaderCache {
-    last_seek_pos: u64,
-    // Contains a capacity for how many files we should have.
+    /// Where the Central Directory starts.
+    start_offset: u64,
+    /// Where the next record starts.
+    next_offset: u64,
+    /// Where the Central Directory ends.
+    end_offset: u64,
+    /// How many records the End of Central Directory record says there are.
+    total_records: u64,
     pub(crate) files: Vec<CentralDirHeader>,
+    fallback_encoding: Option<&'static Encoding>,
+    limits: ArchiveLimits,
+    /// What the records read so far decompress to.
+    total_uncompressed_size: u64,
 }
 
 impl FileReaderCache {
+    /// `start_offset` is where the Central Directory starts in the volume set.
+    pub(crate) fn new(
+        end_header: &EndCentralDirHeader,
+        start_offset: u64,
+        options: ArchiveOptions,
+    ) -> Self {
+        Self {
+            start_offset,
+            next_offset: start_offset,
+            end_offset: start_offset + end_header.size_of,
+            total_records: end_header.total_record_count,
+            files: Vec::with_capacity(end_header.total_record_count.min(u16::MAX as u64) as usize),
+            fallback_encoding: options.fallback_encoding,
+            limits: options.limits,
+            total_uncompressed_size: 0,
+        }
+    }
+
+    /// Already holds every record, like the ones recovered from the Local File Headers.
+    pub(crate) fn from_files(files: Vec<CentralDirHeader>) -> Self {
+        Self {
+            total_records: files.len() as u64,
+            files,
+            ..Self::default()
+        }
+    }
+
     pub fn is_fully_cached(&self) -> bool {
-        self.files.len() == self.files.capacity()
+        self.files.len() as u64 >= self.total_records
     }
 
     pub async fn list_files(
         &mut self,
         reader: &mut ArchiveReader<'_>,
     ) -> Result<Vec<CentralDirHeader>> {
-        let mut items = self.files.clone();
-
         if !self.is_fully_cached() {
-            while let Some(found) = self.find_next(reader).await? {
-                items.push(found.clone());
+            let mut buffer = [0u8; BUFFER_SIZE];
+
+            reader.seek_to(self.next_offset).await?;
+            reader.seek_next(&mut buffer).await?;
+
+            while !self.is_fully_cached() {
+                self.parse_next(reader, &mut buffer).await?;
             }
         }
 
-        Ok(items)
+        Ok(self.files.clone())
     }
 
     pub async fn find_next(
@@ -162,57 +610,107 @@ impl FileReaderCache {
 
         let mut buffer = [0u8; BUFFER_SIZE];
 
-        // TODO: Handle better. I don't want to seek if we don't need to.
-        reader.seek_to(self.last_seek_pos).await?;
+        reader.seek_to(self.next_offset).await?;
+        reader.seek_next(&mut buffer).await?;
 
-        loop {
-            // Read updates seek position
-            reader.last_read_amount = reader.file.read(&mut buffer).await?;
-            reader.index = 0;
+        self.parse_next(reader, &mut buffer).await?;
 
-            if let Some(at_index) = reader.find_next_signature(&buffer, CENTRAL_DIR_SIG) {
-                // Set our current index to where the signature starts.
-                reader.index = at_index;
+        Ok(self.files.last())
+    }
 
-                // trace!("Found Header @ {} {} {:x?}", self.file.stream_position().unwrap() as usize + self.index, self.index, &buffer[self.index..self.index + 4]);
+    /// Parse the record at `next_offset`. The reader has to be positioned there already.
+    async fn parse_next(
+        &mut self,
+        reader: &mut ArchiveReader<'_>,
+        buffer: &mut [u8; BUFFER_SIZE],
+    ) -> Result<()> {
+        let offset = self.next_offset;
+
+        if offset >= self.end_offset {
+            return Err(Error::RecordCountMismatch {
+                expected: self.total_records,
+                found: self.files.len() as u64,
+            });
+        }
+
+        if offset + CENTRAL_DIR_SIZE_KNOWN as u64 > self.end_offset {
+            return Err(Error::CentralDirOverrun {
+                offset,
+                end: self.end_offset,
+            });
+        }
+
+        if reader.index + CENTRAL_DIR_SIZE_KNOWN >= buffer.len() {
+            reader.seek_to_index(buffer).await?;
+        }
 
-                assert_eq!(&buffer[reader.index..reader.index + 4], &CENTRAL_DIR_SIG);
+        if buffer[reader.index..reader.index + SIGNATURE_SIZE] != CENTRAL_DIR_SIG {
+            return Err(Error::MissingCentralDirHeader(offset));
+        }
 
-                // TODO: Remove.
-                if reader.index + CENTRAL_DIR_SIZE_KNOWN >= buffer.len() {
-                    reader.seek_to_index(&mut buffer).await?;
-                }
+        let header = CentralDirHeader::parse(reader, buffer, self.fallback_encoding).await?;
 
-                let header = CentralDirHeader::parse(reader, &mut buffer).await?;
+        let next_offset = offset + header.size();
 
-                // trace!("{header:#?}");
+        if next_offset > self.end_offset {
+            return Err(Error::CentralDirOverrun {
+                offset,
+                end: self.end_offset,
+            });
+        }
 
-                self.files.push(header);
+        self.limits.check_file_name(&header.file_name_raw)?;
+        self.limits
+            .check_ratio(header.compressed_size, header.uncompressed_size)?;
 
-                // Seek position we're at?
-                self.last_seek_pos = reader.get_seek_position().await?;
+        self.total_uncompressed_size = self
+            .total_uncompressed_size
+            .saturating_add(header.uncompressed_size);
+        self.limits.check_total(self.total_uncompressed_size)?;
 
-                return Ok(self.files.last());
-            }
+        self.next_offset = next_offset;
+        self.files.push(header);
 
-            // Nothing left to read?
-            if reader.last_read_amount < buffer.len() {
-                break;
-            }
+        if self.is_fully_cached() {
+            check_overlap(&self.files)?;
+        }
 
-            // We negate the signature size to ensure we didn't get a partial previously. We remove 1 from size to prevent (end of buffer) duplicates.
-            reader
-                .file
-                .seek(SeekFrom::Current(1 - SIGNATURE_SIZE as i64))
-                .await?;
+        // Every record has been read, so there shouldn't be anything left over.
+        if self.is_fully_cached() && self.next_offset != self.end_offset {
+            return Err(Error::CentralDirSizeMismatch {
+                expected: self.end_offset - self.start_offset,
+                found: self.next_offset - self.start_offset,
+            });
         }
 
-        Ok(None)
+        Ok(())
     }
 }
 
+/// Where an entry is stored, and what its Local File Header and data descriptor hold.
+/// See [`CentralDirHeader::extent`].
+#[derive(Debug, Clone)]
+pub(crate) struct EntryExtent {
+    /// Where the Local File Header starts.
+    pub offset: u64,
+    /// The Local File Header, data and data descriptor.
+    pub size: u64,
+    pub local_header: LocalFileHeader,
+    pub data_descriptor: Option<DataDescriptor>,
+}
+
+/// What an entry is. See [`CentralDirHeader::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    /// The contents are the path being linked to.
+    Symlink,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Version {
+    /// The upper byte indicates the host system the file attributes are compatible with. e.g. 0 = MS-DOS, 3 = Unix
     pub compatibility: u8,
     /// The lower byte indicates the ZIP specification version (the version of this document) supported by the software used to encode the file.
     /// The value/10 indicates the major version number,
@@ -230,6 +728,16 @@ impl Version {
             minor: lower % 10,
         }
     }
+
+    /// Are the upper 16 bits of the external file attributes a Unix mode.
+    pub fn is_unix(&self) -> bool {
+        matches!(self.compatibility, HOST_UNIX | HOST_OSX)
+    }
+
+    /// The 2 byte field as it's stored.
+    pub fn to_u16(self) -> u16 {
+        (self.compatibility as u16) << 8 | (self.major as u16 * 10 + self.minor as u16)
+    }
 }
 
 fn chunk_to_version(buffer: &[u8]) -> Version {
@@ -239,14 +747,9 @@ fn chunk_to_version(buffer: &[u8]) -> Version {
 #[derive(Debug, Clone, Copy)]
 pub struct VersionNeeded(u16);
 
-impl VersionNeeded {
-    pub fn is_file(&self) -> bool {
-        !self.is_folder()
-    }
-
-    pub fn is_folder(&self) -> bool {
-        // TODO: Probably more tests
-        self.0 == 20
+impl From<VersionNeeded> for u16 {
+    fn from(value: VersionNeeded) -> Self {
+        value.0
     }
 }
 
@@ -0,0 +1,75 @@
+//! Data Descriptor. Follows the compressed data when bit 3 of the general purpose flag is set.
+
+use crate::{bytes_to_u32, bytes_to_u64};
+
+pub(crate) const DATA_DESCRIPTOR_SIG: [u8; 4] = [0x50, 0x4B, 0x07, 0x08];
+/// Largest possible size. Signature, CRC-32 and two 8 byte sizes.
+pub(crate) const DATA_DESCRIPTOR_MAX_SIZE: usize = 24;
+
+/// Holds the real CRC-32 and sizes when they weren't known while writing the Local File Header.
+///
+/// The signature is optional. The sizes are 8 bytes when the Local File Header contains a Zip64 extra field, otherwise 4 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataDescriptor {
+    /// CRC-32 of uncompressed data
+    pub crc_32: u32,
+    /// Compressed size
+    pub compressed_size: u64,
+    /// Uncompressed size
+    pub uncompressed_size: u64,
+}
+
+impl DataDescriptor {
+    /// Parse the descriptor from the bytes directly after the compressed data.
+    ///
+    /// `compressed_size` is the amount of compressed data which was read.
+    /// It's used to work out if the optional signature is there since a CRC-32 can match the signature.
+    ///
+    /// Returns the descriptor and the amount of bytes it takes up.
+    pub(crate) fn from_bytes(
+        bytes: &[u8],
+        is_zip64: bool,
+        compressed_size: u64,
+    ) -> Option<(Self, usize)> {
+        let with_sig = bytes
+            .starts_with(&DATA_DESCRIPTOR_SIG)
+            .then(|| Self::from_fields(&bytes[4..], is_zip64))
+            .flatten()
+            .map(|(v, size)| (v, size + 4));
+
+        let without_sig = Self::from_fields(bytes, is_zip64);
+
+        match (with_sig, without_sig) {
+            (Some(v), _) if v.0.compressed_size == compressed_size => Some(v),
+            (_, Some(v)) if v.0.compressed_size == compressed_size => Some(v),
+            // Neither matches. Prefer the signature if it was there.
+            (with_sig, without_sig) => with_sig.or(without_sig),
+        }
+    }
+
+    fn from_fields(bytes: &[u8], is_zip64: bool) -> Option<(Self, usize)> {
+        if is_zip64 {
+            let bytes = bytes.get(..20)?;
+
+            Some((
+                Self {
+                    crc_32: bytes_to_u32(&bytes[0..4]),
+                    compressed_size: bytes_to_u64(&bytes[4..12]),
+                    uncompressed_size: bytes_to_u64(&bytes[12..20]),
+                },
+                20,
+            ))
+        } else {
+            let bytes = bytes.get(..12)?;
+
+            Some((
+                Self {
+                    crc_32: bytes_to_u32(&bytes[0..4]),
+                    compressed_size: bytes_to_u32(&bytes[4..8]).into(),
+                    uncompressed_size: bytes_to_u32(&bytes[8..12]).into(),
+                },
+                12,
+            ))
+        }
+    }
+}
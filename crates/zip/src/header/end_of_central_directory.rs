@@ -2,51 +2,76 @@
 
 use std::io::SeekFrom;
 
+use encoding_rs::Encoding;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
-use crate::{ArchiveReader, Error, Result, BUFFER_SIZE, SIGNATURE_SIZE};
+use crate::{
+    bytes_to_u16, bytes_to_u32, text::decode_text, ArchiveReader, Error, Result, BUFFER_SIZE,
+    CENTRAL_DIR_SIZE_KNOWN, SIGNATURE_SIZE,
+};
+
+use super::{
+    Zip64EndCentralDirHeader, Zip64EndCentralDirLocator, ZIP64_END_CENTRAL_DIR_LOCATOR_SIG,
+    ZIP64_END_CENTRAL_DIR_LOCATOR_SIZE, ZIP64_END_CENTRAL_DIR_SIZE_KNOWN,
+};
 
 pub(crate) const END_CENTRAL_DIR_SIG: [u8; 4] = [0x50, 0x4B, 0x05, 0x06];
 pub(crate) const END_CENTRAL_DIR_SIZE_KNOWN: usize = 22;
+/// The record is followed by a comment of up to 65535 bytes, so it has to be within this many bytes from the end.
+pub(crate) const END_CENTRAL_DIR_SEARCH_SIZE: usize =
+    u16::MAX as usize + END_CENTRAL_DIR_SIZE_KNOWN;
 
 /// Used to share the relevant Zip Info.
 #[derive(Debug, Clone)]
 pub struct ArchiveInfo {
     /// Does the zip use multiple disks
     pub is_multi_disk: bool,
+    /// Does the zip use the Zip64 format extensions
+    pub is_zip64: bool,
     /// Total amount of files and folders
-    pub records: u16,
+    pub records: u64,
     /// Size of Central Directory.
-    pub central_dir_size: u32,
+    pub central_dir_size: u64,
     /// Archive Comment, if there is one.
     pub comment: String,
+    /// Archive Comment bytes as they're stored in the record.
+    pub comment_raw: Vec<u8>,
 }
 
 /// Is at the end of every Zip file
+///
+/// If the archive contains a Zip64 End of Central Directory record its values replace the 16 and 32-bit ones.
 #[derive(Debug, Default)]
 pub(crate) struct EndCentralDirHeader {
     // Number of this disk (or 0xffff for ZIP64)
-    pub current_disk_number: u16,
+    pub current_disk_number: u32,
     // Disk where central directory starts (or 0xffff for ZIP64)
-    pub start_disk_number: u16,
+    pub start_disk_number: u32,
     // Number of central directory records on this disk (or 0xffff for ZIP64)
-    pub record_count_on_curr_disk: u16,
+    pub record_count_on_curr_disk: u64,
     // Total number of central directory records (or 0xffff for ZIP64)
-    pub total_record_count: u16,
+    pub total_record_count: u64,
     // Size of central directory (bytes) (or 0xffffffff for ZIP64)
-    pub size_of: u32,
+    pub size_of: u64,
     // Offset of start of central directory, relative to start of archive (or 0xffffffff for ZIP64)
-    pub curr_offset: u32,
+    pub curr_offset: u64,
     // Comment length (n)
     pub comment_len: u16,
-    // Comment
+    // Comment. There's no flag for its encoding so it's decoded as CP437 or the fallback encoding.
     pub comment: String,
+    // Comment bytes
+    pub comment_raw: Vec<u8>,
+    // Offset of this record from the start of the archive
+    pub offset: u64,
+    // Zip64 End of Central Directory record, if the archive has one.
+    pub zip64: Option<Zip64EndCentralDirHeader>,
 }
 
 impl EndCentralDirHeader {
     pub async fn parse(
         reader: &mut ArchiveReader<'_>,
         buffer: &mut [u8; BUFFER_SIZE],
+        fallback_encoding: Option<&'static Encoding>,
     ) -> Result<Self> {
         assert_eq!(
             &buffer[reader.index..reader.index + 4],
@@ -56,80 +81,158 @@ impl EndCentralDirHeader {
         reader.skip::<4>();
 
         let mut header = EndCentralDirHeader {
-            current_disk_number: reader.next_u16(buffer).await?,
-            start_disk_number: reader.next_u16(buffer).await?,
-            record_count_on_curr_disk: reader.next_u16(buffer).await?,
-            total_record_count: reader.next_u16(buffer).await?,
-            size_of: reader.next_u32(buffer).await?,
-            curr_offset: reader.next_u32(buffer).await?,
+            current_disk_number: reader.next_u16(buffer).await?.into(),
+            start_disk_number: reader.next_u16(buffer).await?.into(),
+            record_count_on_curr_disk: reader.next_u16(buffer).await?.into(),
+            total_record_count: reader.next_u16(buffer).await?.into(),
+            size_of: reader.next_u32(buffer).await?.into(),
+            curr_offset: reader.next_u32(buffer).await?.into(),
             comment_len: reader.next_u16(buffer).await?,
             comment: String::new(),
+            comment_raw: Vec::new(),
+            offset: 0,
+            zip64: None,
         };
 
-        header.comment = String::from_utf8(
-            reader
-                .get_chunk_amount(buffer, header.comment_len as usize)
-                .await?,
-        )?;
+        header.comment_raw = reader
+            .get_chunk_amount(buffer, header.comment_len as usize)
+            .await?;
+
+        header.comment = decode_text(&header.comment_raw, false, None, fallback_encoding);
 
         Ok(header)
     }
 
-    pub async fn find(reader: &mut ArchiveReader<'_>) -> Result<EndCentralDirHeader> {
-        let mut buffer = [0u8; BUFFER_SIZE];
+    /// Find the record by reading the end of the file and scanning backwards.
+    ///
+    /// The record can only be followed by its comment, so it has to start within the last 64 KiB + 22 bytes.
+    /// Candidates are checked against the comment length and the Central Directory position, since the signature
+    /// can also show up inside the comment or the entry data.
+    pub async fn find(
+        reader: &mut ArchiveReader<'_>,
+        fallback_encoding: Option<&'static Encoding>,
+    ) -> Result<EndCentralDirHeader> {
+        let file_size = reader.file.seek(SeekFrom::End(0)).await?;
+        let search_start = file_size.saturating_sub(END_CENTRAL_DIR_SEARCH_SIZE as u64);
 
-        // Reset back to start.
-        reader.seek_to(0).await?;
+        reader.file.seek(SeekFrom::Start(search_start)).await?;
 
-        loop {
-            // Read updates seek position
-            reader.last_read_amount = reader.file.read(&mut buffer).await?;
-            reader.index = 0;
+        let mut tail = Vec::with_capacity((file_size - search_start) as usize);
+        reader.file.read_to_end(&mut tail).await?;
 
-            if let Some(at_index) = reader.find_next_signature(&buffer, END_CENTRAL_DIR_SIG) {
-                // Set our current index to where the signature starts.
-                reader.index = at_index;
+        let Some(last_start) = tail.len().checked_sub(END_CENTRAL_DIR_SIZE_KNOWN) else {
+            return Err(Error::MissingEndHeader);
+        };
 
-                // trace!("Found End Header @ {} {} {:x?}", archive.file.stream_position().unwrap() as usize + archive.index, archive.index, &buffer[archive.index..archive.index + 4]);
+        for index in (0..=last_start).rev() {
+            if tail[index..index + SIGNATURE_SIZE] != END_CENTRAL_DIR_SIG {
+                continue;
+            }
 
-                assert_eq!(
-                    &buffer[reader.index..reader.index + 4],
-                    &END_CENTRAL_DIR_SIG
-                );
+            let offset = search_start + index as u64;
 
-                // TODO: Remove.
-                if reader.index + END_CENTRAL_DIR_SIZE_KNOWN >= buffer.len() {
-                    reader.seek_to_index(&mut buffer).await?;
-                }
+            if !Self::is_valid_candidate(&tail[index..], offset) {
+                continue;
+            }
 
-                let header = Self::parse(reader, &mut buffer).await?;
+            let mut buffer = [0u8; BUFFER_SIZE];
 
-                // trace!("{header:#?}");
+            reader.seek_to(offset).await?;
+            reader.seek_next(&mut buffer).await?;
 
-                return Ok(header);
-            }
+            let mut header = Self::parse(reader, &mut buffer, fallback_encoding).await?;
+            header.offset = offset;
 
-            // Nothing left to read?
-            if reader.last_read_amount < buffer.len() {
-                break;
-            }
+            header.find_zip64(reader, &mut buffer).await?;
 
-            // We negate the signature size to ensure we didn't get a partial previously. We remove 1 from size to prevent (end of buffer) duplicates.
-            reader
-                .file
-                .seek(SeekFrom::Current(1 - SIGNATURE_SIZE as i64))
-                .await?;
+            return Ok(header);
         }
 
         Err(Error::MissingEndHeader)
     }
+
+    /// `record` starts at the signature and runs to the end of the file.
+    fn is_valid_candidate(record: &[u8], offset: u64) -> bool {
+        let records = bytes_to_u16(&record[10..12]);
+        let size_of = bytes_to_u32(&record[12..16]);
+        let curr_offset = bytes_to_u32(&record[16..20]);
+        let comment_len = bytes_to_u16(&record[20..22]) as usize;
+
+        // The comment has to fit in what's left of the file.
+        if END_CENTRAL_DIR_SIZE_KNOWN + comment_len > record.len() {
+            return false;
+        }
+
+        // The Zip64 records hold the real values.
+        if records == u16::MAX || size_of == u32::MAX || curr_offset == u32::MAX {
+            return true;
+        }
+
+        // The Central Directory comes before this record and holds at least the minimum size of each entry.
+        u64::from(curr_offset) + u64::from(size_of) <= offset
+            && u64::from(size_of) >= u64::from(records) * CENTRAL_DIR_SIZE_KNOWN as u64
+    }
+
+    /// The Zip64 locator, if it exists, is directly before the End of Central Directory record.
+    async fn find_zip64(
+        &mut self,
+        reader: &mut ArchiveReader<'_>,
+        buffer: &mut [u8; BUFFER_SIZE],
+    ) -> Result<()> {
+        let Some(locator_offset) = self
+            .offset
+            .checked_sub(ZIP64_END_CENTRAL_DIR_LOCATOR_SIZE as u64)
+        else {
+            return Ok(());
+        };
+
+        reader.seek_to(locator_offset).await?;
+        reader.seek_next(buffer).await?;
+
+        if reader.last_read_amount < ZIP64_END_CENTRAL_DIR_LOCATOR_SIZE
+            || buffer[..4] != ZIP64_END_CENTRAL_DIR_LOCATOR_SIG
+        {
+            return Ok(());
+        }
+
+        let locator = Zip64EndCentralDirLocator::parse(reader, buffer).await?;
+
+        let zip64_offset = reader
+            .file
+            .disk_position(locator.disk_number, locator.relative_offset)?;
+
+        reader.seek_to(zip64_offset).await?;
+        reader.seek_next(buffer).await?;
+
+        if reader.last_read_amount < SIGNATURE_SIZE {
+            return Err(Error::MissingZip64EndHeader);
+        }
+
+        let available = locator_offset
+            .saturating_sub(zip64_offset.saturating_add(ZIP64_END_CENTRAL_DIR_SIZE_KNOWN as u64));
+
+        let zip64 = Zip64EndCentralDirHeader::parse(reader, buffer, available).await?;
+
+        self.current_disk_number = zip64.current_disk_number;
+        self.start_disk_number = zip64.start_disk_number;
+        self.record_count_on_curr_disk = zip64.record_count_on_curr_disk;
+        self.total_record_count = zip64.total_record_count;
+        self.size_of = zip64.size_of;
+        self.curr_offset = zip64.curr_offset;
+        self.zip64 = Some(zip64);
+
+        Ok(())
+    }
 }
 
 impl From<&EndCentralDirHeader> for ArchiveInfo {
     fn from(value: &EndCentralDirHeader) -> Self {
         Self {
-            is_multi_disk: value.start_disk_number != value.current_disk_number,
+            // The record is on the last disk.
+            is_multi_disk: value.current_disk_number != 0,
+            is_zip64: value.zip64.is_some(),
             comment: value.comment.clone(),
+            comment_raw: value.comment_raw.clone(),
             central_dir_size: value.size_of,
             records: value.total_record_count,
         }
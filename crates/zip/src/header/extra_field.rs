@@ -0,0 +1,441 @@
+//! Extra Fields found in the Central Directory and Local File Headers.
+
+use crate::{bytes_to_u16, bytes_to_u32, bytes_to_u64, compression::CompressionType};
+
+/// Zip64 extended information extra field
+pub(crate) const ZIP64_EXTRA_ID: u16 = 0x0001;
+/// NTFS extra field
+pub(crate) const NTFS_EXTRA_ID: u16 = 0x000a;
+/// Info-ZIP extended timestamp extra field
+pub(crate) const EXTENDED_TIMESTAMP_EXTRA_ID: u16 = 0x5455;
+/// Info-ZIP Unix UID/GID extra field
+pub(crate) const UNIX_OWNER_EXTRA_ID: u16 = 0x7875;
+/// Info-ZIP Unicode path extra field
+pub(crate) const UNICODE_PATH_EXTRA_ID: u16 = 0x7075;
+/// Info-ZIP Unicode comment extra field
+pub(crate) const UNICODE_COMMENT_EXTRA_ID: u16 = 0x6375;
+/// WinZip AES encryption extra field
+pub(crate) const AES_EXTRA_ID: u16 = 0x9901;
+
+/// Iterate through the extra field records. Each one is a 2 byte header id, a 2 byte data size and the data itself.
+///
+/// A truncated record at the end is ignored.
+pub(crate) fn iter_extra_fields(mut extra: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if extra.len() < 4 {
+            return None;
+        }
+
+        let id = bytes_to_u16(&extra[0..2]);
+        let size = bytes_to_u16(&extra[2..4]) as usize;
+
+        let data = extra.get(4..4 + size)?;
+        extra = &extra[4 + size..];
+
+        Some((id, data))
+    })
+}
+
+/// Remove the records with any of these header ids, keeping the others byte for byte.
+///
+/// A truncated record at the end is kept as it is, unless it has one of the ids.
+pub(crate) fn remove_extra_fields(mut extra: &[u8], ids: &[u16]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(extra.len());
+
+    while extra.len() >= 4 {
+        let id = bytes_to_u16(&extra[0..2]);
+        let size = (4 + bytes_to_u16(&extra[2..4]) as usize).min(extra.len());
+
+        if !ids.contains(&id) {
+            output.extend_from_slice(&extra[..size]);
+        }
+
+        extra = &extra[size..];
+    }
+
+    output.extend_from_slice(extra);
+
+    output
+}
+
+/// Which header fields were set to -1 (0xFFFF or 0xFFFFFFFF). Only those are stored in the Zip64 extra field.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Zip64Fields {
+    pub uncompressed_size: bool,
+    pub compressed_size: bool,
+    pub relative_offset: bool,
+    pub disk_start_number: bool,
+}
+
+/// A single Extra Field record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtraField {
+    /// Zip64 Extended Information (0x0001)
+    Zip64(Zip64ExtendedInfo),
+    /// NTFS Timestamps (0x000a)
+    Ntfs(NtfsTimestamps),
+    /// Info-ZIP Extended Timestamp (0x5455)
+    ExtendedTimestamp(ExtendedTimestamp),
+    /// Info-ZIP Unix UID/GID (0x7875)
+    UnixOwner(UnixOwner),
+    /// Info-ZIP Unicode Path (0x7075)
+    UnicodePath(UnicodeExtraField),
+    /// Info-ZIP Unicode Comment (0x6375)
+    UnicodeComment(UnicodeExtraField),
+    /// WinZip AES (0x9901)
+    Aes(AesExtraField),
+    /// Any other record, or one which couldn't be decoded.
+    Unknown { id: u16, data: Vec<u8> },
+}
+
+impl ExtraField {
+    /// Decode every record in the extra field.
+    pub(crate) fn parse_all(extra: &[u8], zip64: Zip64Fields) -> Vec<Self> {
+        iter_extra_fields(extra)
+            .map(|(id, data)| Self::parse(id, data, zip64))
+            .collect()
+    }
+
+    /// Decode a single record. It's kept as [`ExtraField::Unknown`] if it couldn't be decoded.
+    pub(crate) fn parse(id: u16, data: &[u8], zip64: Zip64Fields) -> Self {
+        let parsed = match id {
+            ZIP64_EXTRA_ID => Some(Self::Zip64(Zip64ExtendedInfo::from_bytes(data, zip64))),
+            NTFS_EXTRA_ID => NtfsTimestamps::from_bytes(data).map(Self::Ntfs),
+            EXTENDED_TIMESTAMP_EXTRA_ID => {
+                ExtendedTimestamp::from_bytes(data).map(Self::ExtendedTimestamp)
+            }
+            UNIX_OWNER_EXTRA_ID => UnixOwner::from_bytes(data).map(Self::UnixOwner),
+            UNICODE_PATH_EXTRA_ID => UnicodeExtraField::from_bytes(data).map(Self::UnicodePath),
+            UNICODE_COMMENT_EXTRA_ID => {
+                UnicodeExtraField::from_bytes(data).map(Self::UnicodeComment)
+            }
+            AES_EXTRA_ID => AesExtraField::from_bytes(data).map(Self::Aes),
+            _ => None,
+        };
+
+        parsed.unwrap_or_else(|| Self::Unknown {
+            id,
+            data: data.to_vec(),
+        })
+    }
+
+    /// The header id of the record.
+    pub fn id(&self) -> u16 {
+        match self {
+            Self::Zip64(_) => ZIP64_EXTRA_ID,
+            Self::Ntfs(_) => NTFS_EXTRA_ID,
+            Self::ExtendedTimestamp(_) => EXTENDED_TIMESTAMP_EXTRA_ID,
+            Self::UnixOwner(_) => UNIX_OWNER_EXTRA_ID,
+            Self::UnicodePath(_) => UNICODE_PATH_EXTRA_ID,
+            Self::UnicodeComment(_) => UNICODE_COMMENT_EXTRA_ID,
+            Self::Aes(_) => AES_EXTRA_ID,
+            Self::Unknown { id, .. } => *id,
+        }
+    }
+
+    /// The record data, without the header id and data size.
+    pub fn data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        match self {
+            Self::Zip64(v) => v.write(&mut data),
+            Self::Ntfs(v) => v.write(&mut data),
+            Self::ExtendedTimestamp(v) => v.write(&mut data),
+            Self::UnixOwner(v) => v.write(&mut data),
+            Self::UnicodePath(v) | Self::UnicodeComment(v) => v.write(&mut data),
+            Self::Aes(v) => v.write(&mut data),
+            Self::Unknown { data: v, .. } => data.extend_from_slice(v),
+        }
+
+        data
+    }
+
+    /// Append the whole record to `output`.
+    pub fn write(&self, output: &mut Vec<u8>) {
+        let data = self.data();
+
+        output.extend_from_slice(&self.id().to_le_bytes());
+        output.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        output.extend_from_slice(&data);
+    }
+
+    /// Encode the records into the bytes of an extra field.
+    pub fn to_bytes(fields: &[Self]) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        for field in fields {
+            field.write(&mut output);
+        }
+
+        output
+    }
+}
+
+/// Zip64 Extended Information Extra Field (0x0001)
+///
+/// A value is only stored if the matching header field is set to -1 (0xFFFF or 0xFFFFFFFF).
+/// The values which are stored MUST appear in the order they're defined here.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Zip64ExtendedInfo {
+    /// Original uncompressed file size
+    pub uncompressed_size: Option<u64>,
+    /// Size of compressed data
+    pub compressed_size: Option<u64>,
+    /// Offset of local header record
+    pub relative_offset: Option<u64>,
+    /// Number of the disk on which this file starts
+    pub disk_start_number: Option<u32>,
+}
+
+impl Zip64ExtendedInfo {
+    /// Parse the values for the header fields which were saturated.
+    pub(crate) fn from_bytes(mut data: &[u8], fields: Zip64Fields) -> Self {
+        let mut next_u64 = |wanted: bool| {
+            if wanted && data.len() >= 8 {
+                let value = bytes_to_u64(&data[..8]);
+                data = &data[8..];
+                Some(value)
+            } else {
+                None
+            }
+        };
+
+        let mut this = Self {
+            uncompressed_size: next_u64(fields.uncompressed_size),
+            compressed_size: next_u64(fields.compressed_size),
+            relative_offset: next_u64(fields.relative_offset),
+            disk_start_number: None,
+        };
+
+        if fields.disk_start_number && data.len() >= 4 {
+            this.disk_start_number = Some(bytes_to_u32(&data[..4]));
+        }
+
+        this
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        for value in [
+            self.uncompressed_size,
+            self.compressed_size,
+            self.relative_offset,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+
+        if let Some(value) = self.disk_start_number {
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// NTFS Extra Field (0x000a)
+///
+/// Times are the amount of 100 nanosecond intervals since 1601-01-01 UTC.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NtfsTimestamps {
+    /// File last modification time
+    pub last_modified: u64,
+    /// File last access time
+    pub last_accessed: u64,
+    /// File creation time
+    pub created: u64,
+}
+
+impl NtfsTimestamps {
+    /// The times are in attribute tag 0x0001 which follows 4 reserved bytes.
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (_, data) = iter_extra_fields(data.get(4..)?).find(|(tag, _)| *tag == 0x0001)?;
+        let data = data.get(..24)?;
+
+        Some(Self {
+            last_modified: bytes_to_u64(&data[0..8]),
+            last_accessed: bytes_to_u64(&data[8..16]),
+            created: bytes_to_u64(&data[16..24]),
+        })
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&[0; 4]);
+        output.extend_from_slice(&1u16.to_le_bytes());
+        output.extend_from_slice(&24u16.to_le_bytes());
+        output.extend_from_slice(&self.last_modified.to_le_bytes());
+        output.extend_from_slice(&self.last_accessed.to_le_bytes());
+        output.extend_from_slice(&self.created.to_le_bytes());
+    }
+}
+
+/// Info-ZIP Extended Timestamp Extra Field (0x5455)
+///
+/// Times are Unix timestamps in seconds, UTC.
+/// The Central Directory version only holds the modification time, but the flags still state which times the Local File Header has.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedTimestamp {
+    /// Bit 0: modification time, bit 1: access time, bit 2: creation time
+    pub flags: u8,
+    /// File last modification time
+    pub last_modified: Option<i32>,
+    /// File last access time
+    pub last_accessed: Option<i32>,
+    /// File creation time
+    pub created: Option<i32>,
+}
+
+impl ExtendedTimestamp {
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (&flags, mut data) = data.split_first()?;
+
+        let mut next_i32 = |bit: u8| {
+            if flags & bit != 0 && data.len() >= 4 {
+                let value = bytes_to_u32(&data[..4]) as i32;
+                data = &data[4..];
+                Some(value)
+            } else {
+                None
+            }
+        };
+
+        Some(Self {
+            flags,
+            last_modified: next_i32(1 << 0),
+            last_accessed: next_i32(1 << 1),
+            created: next_i32(1 << 2),
+        })
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        output.push(self.flags);
+
+        for value in [self.last_modified, self.last_accessed, self.created]
+            .into_iter()
+            .flatten()
+        {
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Info-ZIP New Unix Extra Field (0x7875)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UnixOwner {
+    /// Currently 1
+    pub version: u8,
+    /// User ID
+    pub uid: u32,
+    /// Group ID
+    pub gid: u32,
+}
+
+impl UnixOwner {
+    /// Each ID is stored after its own size. Only sizes up to 4 bytes are supported.
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (&version, data) = data.split_first()?;
+
+        let (uid, data) = read_sized_id(data)?;
+        let (gid, _) = read_sized_id(data)?;
+
+        Some(Self { version, uid, gid })
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        output.push(self.version);
+        output.push(4);
+        output.extend_from_slice(&self.uid.to_le_bytes());
+        output.push(4);
+        output.extend_from_slice(&self.gid.to_le_bytes());
+    }
+}
+
+fn read_sized_id(data: &[u8]) -> Option<(u32, &[u8])> {
+    let (&size, data) = data.split_first()?;
+    let size = size as usize;
+
+    if size > 4 {
+        return None;
+    }
+
+    let value = data
+        .get(..size)?
+        .iter()
+        .rev()
+        .fold(0u32, |value, byte| (value << 8) | u32::from(*byte));
+
+    Some((value, &data[size..]))
+}
+
+/// Info-ZIP Unicode Path (0x7075) and Unicode Comment (0x6375) Extra Fields
+///
+/// Holds the UTF-8 version of the file name or comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnicodeExtraField {
+    /// Currently 1
+    pub version: u8,
+    /// CRC-32 of the file name or comment in the header.
+    /// If it doesn't match, the header was changed by something which didn't update this field so the value shouldn't be used.
+    pub crc_32: u32,
+    /// The UTF-8 file name or comment
+    pub value: String,
+}
+
+impl UnicodeExtraField {
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 5 {
+            return None;
+        }
+
+        Some(Self {
+            version: data[0],
+            crc_32: bytes_to_u32(&data[1..5]),
+            value: String::from_utf8(data[5..].to_vec()).ok()?,
+        })
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        output.push(self.version);
+        output.extend_from_slice(&self.crc_32.to_le_bytes());
+        output.extend_from_slice(self.value.as_bytes());
+    }
+}
+
+/// WinZip AES Extra Field (0x9901)
+///
+/// Found on entries which use compression method 99. The real compression method is stored here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AesExtraField {
+    /// AE-1 (1) or AE-2 (2). AE-2 doesn't store the CRC-32.
+    pub version: u16,
+    /// Always "AE"
+    pub vendor_id: [u8; 2],
+    /// 1 = 128-bit, 2 = 192-bit, 3 = 256-bit
+    pub strength: u8,
+    /// The compression method used before the data was encrypted.
+    pub compression: CompressionType,
+}
+
+impl AesExtraField {
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let data = data.get(..7)?;
+
+        Some(Self {
+            version: bytes_to_u16(&data[0..2]),
+            vendor_id: [data[2], data[3]],
+            strength: data[4],
+            compression: CompressionType::try_from(bytes_to_u16(&data[5..7])).ok()?,
+        })
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.version.to_le_bytes());
+        output.extend_from_slice(&self.vendor_id);
+        output.push(self.strength);
+        output.extend_from_slice(&u16::from(self.compression).to_le_bytes());
+    }
+
+    /// Is the CRC-32 stored. With AE-2 it's zero and the authentication code is relied upon instead.
+    pub fn has_crc_32(&self) -> bool {
+        self.version != 2
+    }
+}
@@ -1,51 +1,68 @@
-use tokio::io::AsyncReadExt;
+use std::time::SystemTime;
 
-use crate::{ArchiveReader, CompressionType, Result, BUFFER_SIZE};
+use encoding_rs::Encoding;
+
+use crate::{
+    text::decode_text, time::extra_field_times, ArchiveReader, CompressionType, DosDateTime,
+    EntryTime, Error, Result, BUFFER_SIZE,
+};
+
+use super::{ExtraField, Zip64Fields, GP_FLAG_DATA_DESCRIPTOR, GP_FLAG_ENCRYPTED, GP_FLAG_UTF8};
 
 pub(crate) const LOCAL_FILE_HEADER_SIG: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+pub(crate) const LOCAL_FILE_HEADER_SIZE_KNOWN: usize = 30;
 
-#[derive(Debug)]
+/// Placed before the data of each file.
+#[derive(Debug, Clone)]
 pub struct LocalFileHeader {
-    // Version needed to extract (minimum)
-    min_version: u16,
-    // General purpose bit flag
-    gp_flag: u16,
-    // Compression method; e.g. none = 0, DEFLATE = 8 (or "\0x08\0x00")
-    compression: CompressionType,
-    // File last modification time
-    file_last_mod_time: u16,
-    // File last modification date
-    file_last_mod_date: u16,
-    // CRC-32 of uncompressed data
-    crc_32: u32,
-    // Compressed size (or 0xffffffff for ZIP64)
-    compressed_size: u32,
-    // Uncompressed size (or 0xffffffff for ZIP64)
-    uncompressed_size: u32,
-    // File name length (n)
-    file_name_length: u16,
-    // Extra field length (m)
-    extra_field_length: u16,
-    // File name
-    file_name: String,
-    // Extra field
-    extra_field: Vec<(u16, u16)>,
+    /// Version needed to extract (minimum)
+    pub min_version: u16,
+    /// General purpose bit flag
+    pub gp_flag: u16,
+    /// Compression method; e.g. none = 0, DEFLATE = 8 (or "\0x08\0x00")
+    pub compression: CompressionType,
+    /// File last modification time
+    pub file_last_mod_time: u16,
+    /// File last modification date
+    pub file_last_mod_date: u16,
+    /// CRC-32 of uncompressed data (zero if it's in the data descriptor)
+    pub crc_32: u32,
+    /// Compressed size (read from the Zip64 extra field when the header holds 0xffffffff)
+    pub compressed_size: u64,
+    /// Uncompressed size (read from the Zip64 extra field when the header holds 0xffffffff)
+    pub uncompressed_size: u64,
+    /// File name length (n)
+    pub file_name_length: u16,
+    /// Extra field length (m)
+    pub extra_field_length: u16,
+    /// File name. Decoded the same way as the Central Directory file name.
+    pub file_name: String,
+    /// File name bytes as they're stored in the header
+    pub file_name_raw: Vec<u8>,
+    /// Extra field
+    pub extra_field: Vec<ExtraField>,
+    /// Extra field bytes as they're stored in the header
+    pub extra_field_raw: Vec<u8>,
+    /// Contains the Zip64 extended information extra field. The data descriptor will use 8 byte sizes.
+    pub is_zip64: bool,
 }
 
 impl LocalFileHeader {
     pub async fn parse(
         reader: &mut ArchiveReader<'_>,
         start_offset: u64,
-    ) -> Result<(Self, String)> {
+        fallback_encoding: Option<&'static Encoding>,
+    ) -> Result<Self> {
         let mut buffer = [0u8; BUFFER_SIZE];
 
         reader.seek_to(start_offset).await?;
-        reader.last_read_amount = reader.file.read(&mut buffer).await?;
+        reader.seek_next(&mut buffer).await?;
 
-        assert_eq!(
-            &buffer[reader.index..reader.index + 4],
-            &LOCAL_FILE_HEADER_SIG
-        );
+        if reader.last_read_amount < LOCAL_FILE_HEADER_SIZE_KNOWN
+            || buffer[reader.index..reader.index + 4] != LOCAL_FILE_HEADER_SIG
+        {
+            return Err(Error::MissingLocalHeader);
+        }
 
         reader.skip::<4>();
 
@@ -56,39 +73,96 @@ impl LocalFileHeader {
             file_last_mod_time: reader.next_u16(&mut buffer).await?,
             file_last_mod_date: reader.next_u16(&mut buffer).await?,
             crc_32: reader.next_u32(&mut buffer).await?,
-            compressed_size: reader.next_u32(&mut buffer).await?,
-            uncompressed_size: reader.next_u32(&mut buffer).await?,
+            compressed_size: reader.next_u32(&mut buffer).await?.into(),
+            uncompressed_size: reader.next_u32(&mut buffer).await?.into(),
             file_name_length: reader.next_u16(&mut buffer).await?,
             extra_field_length: reader.next_u16(&mut buffer).await?,
             file_name: String::new(),
+            file_name_raw: Vec::new(),
             extra_field: Vec::new(),
+            extra_field_raw: Vec::new(),
+            is_zip64: false,
         };
 
-        header.file_name = String::from_utf8(
-            reader
-                .get_chunk_amount(&mut buffer, header.file_name_length as usize)
-                .await?,
-        )?;
-        header.extra_field = reader
+        header.file_name_raw = reader
+            .get_chunk_amount(&mut buffer, header.file_name_length as usize)
+            .await?;
+
+        header.extra_field_raw = reader
             .get_chunk_amount(&mut buffer, header.extra_field_length as usize)
-            .await?
-            .into_iter()
-            .array_chunks::<4>()
-            .map(|v| {
-                (
-                    (u16::from(v[0]) << 8) | u16::from(v[1]),
-                    (u16::from(v[2]) << 8) | u16::from(v[3]),
-                )
-            })
-            .collect();
-
-        let comp_contents = reader
-            .get_chunk_amount(&mut buffer, header.compressed_size as usize)
             .await?;
-        let contents = header.compression.decompress(comp_contents)?;
+
+        // The Local Header Zip64 field MUST include BOTH original and compressed file size fields.
+        header.extra_field = ExtraField::parse_all(
+            &header.extra_field_raw,
+            Zip64Fields {
+                uncompressed_size: true,
+                compressed_size: true,
+                ..Zip64Fields::default()
+            },
+        );
+
+        if let Some(zip64) = header.extra_field.iter().find_map(|v| match v {
+            ExtraField::Zip64(v) => Some(*v),
+            _ => None,
+        }) {
+            header.is_zip64 = true;
+
+            if header.uncompressed_size == u32::MAX as u64 {
+                header.uncompressed_size =
+                    zip64.uncompressed_size.unwrap_or(header.uncompressed_size);
+            }
+
+            if header.compressed_size == u32::MAX as u64 {
+                header.compressed_size = zip64.compressed_size.unwrap_or(header.compressed_size);
+            }
+        }
+
+        header.file_name = decode_text(
+            &header.file_name_raw,
+            header.gp_flag & GP_FLAG_UTF8 != 0,
+            header.extra_field.iter().find_map(|v| match v {
+                ExtraField::UnicodePath(v) => Some(v),
+                _ => None,
+            }),
+            fallback_encoding,
+        );
 
         // TODO: Determine what we want to do with the Header. It's just a shrunken form of Central Directory File Header.
 
-        Ok((header, contents))
+        Ok(header)
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.gp_flag & GP_FLAG_ENCRYPTED != 0
+    }
+
+    /// Are the CRC-32 and sizes in a data descriptor after the compressed data.
+    pub fn has_data_descriptor(&self) -> bool {
+        self.gp_flag & GP_FLAG_DATA_DESCRIPTOR != 0
+    }
+
+    /// The last modification time. The NTFS or extended timestamp extra fields are used when present,
+    /// otherwise it's the MS-DOS date and time.
+    pub fn last_modified(&self) -> EntryTime {
+        match extra_field_times(&self.extra_field).0 {
+            Some(v) => EntryTime::Utc(v),
+            None => EntryTime::Local(DosDateTime::from_fields(
+                self.file_last_mod_time,
+                self.file_last_mod_date,
+            )),
+        }
+    }
+
+    /// The last access time from the NTFS or extended timestamp extra fields.
+    pub fn last_accessed(&self) -> Option<SystemTime> {
+        extra_field_times(&self.extra_field).1
+    }
+
+    /// Size of the header. The entry data starts directly after it.
+    pub fn size(&self) -> u64 {
+        (LOCAL_FILE_HEADER_SIZE_KNOWN
+            + self.file_name_length as usize
+            + self.extra_field_length as usize) as u64
     }
 }
@@ -1,10 +1,50 @@
 mod central_directory_file;
+mod data_descriptor;
 mod end_of_central_directory;
+mod extra_field;
 mod local_file;
+mod zip64_end_of_central_directory;
 
 pub use central_directory_file::*;
+pub use data_descriptor::*;
 pub use end_of_central_directory::*;
+pub use extra_field::*;
 pub use local_file::*;
+pub(crate) use zip64_end_of_central_directory::*;
+
+/// General purpose bit 0: The file is encrypted.
+pub(crate) const GP_FLAG_ENCRYPTED: u16 = 1 << 0;
+/// General purpose bit 1: Implode used an 8K sliding dictionary rather than 4K.
+pub(crate) const GP_FLAG_IMPLODE_8K_DICTIONARY: u16 = 1 << 1;
+/// General purpose bit 2: Implode used 3 Shannon-Fano trees, including one for literals, rather than 2.
+pub(crate) const GP_FLAG_IMPLODE_3_TREES: u16 = 1 << 2;
+/// General purpose bit 1: LZMA data ends with an end of stream marker.
+pub(crate) const GP_FLAG_LZMA_END_MARKER: u16 = 1 << 1;
+/// General purpose bits 1 and 2: The Deflate compression option. 0 = Normal, 1 = Maximum, 2 = Fast, 3 = Super Fast.
+pub(crate) const GP_FLAG_DEFLATE_OPTION_SHIFT: u16 = 1;
+/// General purpose bit 3: The CRC-32 and sizes are in a data descriptor after the compressed data.
+pub(crate) const GP_FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+/// General purpose bit 6: Strong encryption.
+pub(crate) const GP_FLAG_STRONG_ENCRYPTION: u16 = 1 << 6;
+/// General purpose bit 11: The file name and comment are UTF-8.
+pub(crate) const GP_FLAG_UTF8: u16 = 1 << 11;
+
+/// Version made by host system: Unix. The upper 16 bits of the external file attributes are the mode.
+pub(crate) const HOST_UNIX: u8 = 3;
+/// Version made by host system: OS X (Darwin). Some writers use it with a Unix mode as well.
+pub(crate) const HOST_OSX: u8 = 19;
+
+/// MS-DOS directory attribute. The lower byte of the external file attributes.
+pub(crate) const DOS_DIRECTORY_ATTR: u32 = 0x10;
+
+/// Unix file type bits of the mode (S_IFMT).
+pub(crate) const UNIX_FILE_TYPE_MASK: u32 = 0o170000;
+/// Unix file type: directory (S_IFDIR).
+pub(crate) const UNIX_DIRECTORY: u32 = 0o040000;
+/// Unix file type: symbolic link (S_IFLNK).
+pub(crate) const UNIX_SYMLINK: u32 = 0o120000;
+/// Unix permission bits, including setuid, setgid and sticky.
+pub(crate) const UNIX_PERMISSIONS_MASK: u32 = 0o7777;
 
 // 4.4.1.1  All fields unless otherwise noted are unsigned and stored in Intel low-byte:high-byte, low-word:high-word order.
 // 4.4.1.2  String fields are not null terminated, since the length is given explicitly.
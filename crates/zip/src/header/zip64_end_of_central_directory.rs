@@ -0,0 +1,106 @@
+//! The Zip64 EOCD record and locator. Only found in archives which need the extra space.
+
+use crate::{ArchiveReader, Error, Result, BUFFER_SIZE};
+
+pub(crate) const ZIP64_END_CENTRAL_DIR_SIG: [u8; 4] = [0x50, 0x4B, 0x06, 0x06];
+pub(crate) const ZIP64_END_CENTRAL_DIR_SIZE_KNOWN: usize = 56;
+
+pub(crate) const ZIP64_END_CENTRAL_DIR_LOCATOR_SIG: [u8; 4] = [0x50, 0x4B, 0x06, 0x07];
+pub(crate) const ZIP64_END_CENTRAL_DIR_LOCATOR_SIZE: usize = 20;
+
+/// Placed immediately before the End of Central Directory record.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Zip64EndCentralDirLocator {
+    // Number of the disk with the start of the zip64 end of central directory
+    pub disk_number: u32,
+    // Relative offset of the zip64 end of central directory record
+    pub relative_offset: u64,
+    // Total number of disks
+    pub total_disks: u32,
+}
+
+impl Zip64EndCentralDirLocator {
+    pub async fn parse(
+        reader: &mut ArchiveReader<'_>,
+        buffer: &mut [u8; BUFFER_SIZE],
+    ) -> Result<Self> {
+        assert_eq!(
+            &buffer[reader.index..reader.index + 4],
+            &ZIP64_END_CENTRAL_DIR_LOCATOR_SIG
+        );
+
+        reader.skip::<4>();
+
+        Ok(Self {
+            disk_number: reader.next_u32(buffer).await?,
+            relative_offset: reader.next_u64(buffer).await?,
+            total_disks: reader.next_u32(buffer).await?,
+        })
+    }
+}
+
+/// Zip64 End of Central Directory record. Holds the 64-bit values of the End of Central Directory record.
+#[derive(Debug, Clone)]
+pub(crate) struct Zip64EndCentralDirHeader {
+    // Size of zip64 end of central directory record (excludes the leading 12 bytes)
+    pub size_of_record: u64,
+    // Version made by
+    pub by_version: u16,
+    // Version needed to extract
+    pub min_version: u16,
+    // Number of this disk
+    pub current_disk_number: u32,
+    // Number of the disk with the start of the central directory
+    pub start_disk_number: u32,
+    // Total number of entries in the central directory on this disk
+    pub record_count_on_curr_disk: u64,
+    // Total number of entries in the central directory
+    pub total_record_count: u64,
+    // Size of the central directory
+    pub size_of: u64,
+    // Offset of start of central directory with respect to the starting disk number
+    pub curr_offset: u64,
+    // Zip64 extensible data sector
+    pub extensible_data: Vec<u8>,
+}
+
+impl Zip64EndCentralDirHeader {
+    /// The extensible data has to fit in the `available` bytes before the locator, since its size can't be trusted.
+    pub async fn parse(
+        reader: &mut ArchiveReader<'_>,
+        buffer: &mut [u8; BUFFER_SIZE],
+        available: u64,
+    ) -> Result<Self> {
+        if buffer[reader.index..reader.index + 4] != ZIP64_END_CENTRAL_DIR_SIG {
+            return Err(Error::MissingZip64EndHeader);
+        }
+
+        reader.skip::<4>();
+
+        let mut header = Self {
+            size_of_record: reader.next_u64(buffer).await?,
+            by_version: reader.next_u16(buffer).await?,
+            min_version: reader.next_u16(buffer).await?,
+            current_disk_number: reader.next_u32(buffer).await?,
+            start_disk_number: reader.next_u32(buffer).await?,
+            record_count_on_curr_disk: reader.next_u64(buffer).await?,
+            total_record_count: reader.next_u64(buffer).await?,
+            size_of: reader.next_u64(buffer).await?,
+            curr_offset: reader.next_u64(buffer).await?,
+            extensible_data: Vec::new(),
+        };
+
+        // The record size doesn't include the signature or the size field itself.
+        let known_size = ZIP64_END_CENTRAL_DIR_SIZE_KNOWN as u64 - 12;
+
+        let size = header.size_of_record.saturating_sub(known_size);
+
+        if size > available {
+            return Err(Error::Zip64EndHeaderOverrun { size, available });
+        }
+
+        header.extensible_data = reader.get_chunk_amount(buffer, size as usize).await?;
+
+        Ok(header)
+    }
+}
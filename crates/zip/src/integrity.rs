@@ -0,0 +1,233 @@
+//! Tests an archive without extracting anything, like `unzip -t`.
+//!
+//! Every entry is decompressed and thrown away, which checks it against its CRC-32 and sizes. Its Local File
+//! Header and data descriptor are compared with its Central Directory record too, since a reader which walks the
+//! Local File Headers would otherwise see a different archive to one which uses the Central Directory.
+
+use std::collections::HashSet;
+
+use tokio::io;
+
+use crate::{
+    compression::CompressionType, Archive, CentralDirHeader, EntryExtent, Error, Result,
+    GP_FLAG_DATA_DESCRIPTOR, GP_FLAG_ENCRYPTED, GP_FLAG_UTF8,
+};
+
+/// Bits 1 and 2 hold the compression options.
+const GP_FLAG_COMPRESSION_OPTIONS: u16 = 0b110;
+/// The general purpose flags which have to be the same in both headers.
+const GP_FLAG_CHECKED: u16 =
+    GP_FLAG_ENCRYPTED | GP_FLAG_COMPRESSION_OPTIONS | GP_FLAG_DATA_DESCRIPTOR | GP_FLAG_UTF8;
+
+/// What was found while testing an archive.
+#[derive(Debug, Default)]
+pub struct TestReport {
+    /// In the order of the Central Directory.
+    pub entries: Vec<EntryReport>,
+    /// Problems with the archive as a whole.
+    pub findings: Vec<ArchiveFinding>,
+}
+
+impl TestReport {
+    /// Did every entry pass, with nothing wrong with the archive.
+    pub fn is_ok(&self) -> bool {
+        self.findings.is_empty() && self.entries.iter().all(EntryReport::is_ok)
+    }
+}
+
+#[derive(Debug)]
+pub struct EntryReport {
+    pub file_name: String,
+    /// Where the Local File Header, or data descriptor, disagrees with the Central Directory.
+    pub mismatches: Vec<HeaderMismatch>,
+    /// Why the entry couldn't be read, or what didn't match once it was decompressed.
+    pub error: Option<Error>,
+}
+
+impl EntryReport {
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty() && self.error.is_none()
+    }
+}
+
+/// A value which is different in the Local File Header than in the Central Directory.
+///
+/// The CRC-32 and sizes are compared with the data descriptor instead when the entry has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderMismatch {
+    FileName {
+        central: String,
+        local: String,
+    },
+    Compression {
+        central: CompressionType,
+        local: CompressionType,
+    },
+    /// Only the encryption, compression option, data descriptor and UTF-8 flags are compared.
+    Flags {
+        central: u16,
+        local: u16,
+    },
+    Crc32 {
+        central: u32,
+        local: u32,
+    },
+    CompressedSize {
+        central: u64,
+        local: u64,
+    },
+    UncompressedSize {
+        central: u64,
+        local: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveFinding {
+    /// More than one entry has this name. Which one is extracted depends on the tool.
+    DuplicateFileName(String),
+    /// Bytes before the Central Directory which aren't part of any entry, e.g. a self-extractor stub.
+    UnaccountedBytes { offset: u64, size: u64 },
+}
+
+impl Archive {
+    /// Decompress every entry without writing it anywhere, and cross-check the headers.
+    ///
+    /// Encrypted entries fail with [`Error::PasswordRequired`]. Errors are only returned when the Central
+    /// Directory can't be read.
+    pub async fn test(&mut self) -> Result<TestReport> {
+        self.test_entries(None).await
+    }
+
+    pub async fn test_with_password(&mut self, password: impl AsRef<[u8]>) -> Result<TestReport> {
+        self.test_entries(Some(password.as_ref())).await
+    }
+
+    async fn test_entries(&mut self, password: Option<&[u8]>) -> Result<TestReport> {
+        let files = self.list_files().await?;
+
+        let mut report = TestReport::default();
+        // Where each entry is stored, to find anything in between.
+        let mut ranges = Vec::with_capacity(files.len());
+        let mut names = HashSet::with_capacity(files.len());
+
+        for file in &files {
+            if !names.insert(file.file_name_raw.as_slice()) {
+                report
+                    .findings
+                    .push(ArchiveFinding::DuplicateFileName(file.file_name.clone()));
+            }
+
+            let mut entry = EntryReport {
+                file_name: file.file_name.clone(),
+                mismatches: Vec::new(),
+                error: None,
+            };
+
+            match file.extent(self).await {
+                Ok(extent) => {
+                    ranges.push((extent.offset, extent.offset + extent.size));
+                    entry.mismatches = compare_headers(file, &extent);
+
+                    entry.error = self.test_data(file, password).await.err();
+                }
+
+                Err(e) => entry.error = Some(e),
+            }
+
+            report.entries.push(entry);
+        }
+
+        ranges.sort();
+
+        let central_dir_offset = self.file.disk_position(
+            self.end_header.start_disk_number,
+            self.end_header.curr_offset,
+        )?;
+
+        let mut offset = self.file.spanning_marker_size().await?;
+
+        for (start, end) in ranges
+            .into_iter()
+            .chain([(central_dir_offset, central_dir_offset)])
+        {
+            if start > offset {
+                report.findings.push(ArchiveFinding::UnaccountedBytes {
+                    offset,
+                    size: start - offset,
+                });
+            }
+
+            offset = offset.max(end);
+        }
+
+        Ok(report)
+    }
+
+    /// Decompress the entry into nothing. The reader checks the CRC-32 and size once it reaches the end.
+    async fn test_data(&mut self, file: &CentralDirHeader, password: Option<&[u8]>) -> Result<()> {
+        let mut reader = match password {
+            Some(password) => file.reader_with_password(self, password).await?,
+            None => file.reader(self).await?,
+        };
+
+        io::copy(&mut reader, &mut io::sink()).await?;
+
+        Ok(())
+    }
+}
+
+fn compare_headers(central: &CentralDirHeader, extent: &EntryExtent) -> Vec<HeaderMismatch> {
+    let local = &extent.local_header;
+    let mut mismatches = Vec::new();
+
+    // The names are compared as they're stored, since a Unicode Path extra field can be in either header.
+    if central.file_name_raw != local.file_name_raw {
+        mismatches.push(HeaderMismatch::FileName {
+            central: central.file_name.clone(),
+            local: local.file_name.clone(),
+        });
+    }
+
+    if central.compression != local.compression {
+        mismatches.push(HeaderMismatch::Compression {
+            central: central.compression,
+            local: local.compression,
+        });
+    }
+
+    if central.gp_flag & GP_FLAG_CHECKED != local.gp_flag & GP_FLAG_CHECKED {
+        mismatches.push(HeaderMismatch::Flags {
+            central: central.gp_flag,
+            local: local.gp_flag,
+        });
+    }
+
+    let (crc_32, compressed_size, uncompressed_size) = match extent.data_descriptor {
+        Some(v) => (v.crc_32, v.compressed_size, v.uncompressed_size),
+        None => (local.crc_32, local.compressed_size, local.uncompressed_size),
+    };
+
+    if central.crc_32 != crc_32 {
+        mismatches.push(HeaderMismatch::Crc32 {
+            central: central.crc_32,
+            local: crc_32,
+        });
+    }
+
+    if central.compressed_size != compressed_size {
+        mismatches.push(HeaderMismatch::CompressedSize {
+            central: central.compressed_size,
+            local: compressed_size,
+        });
+    }
+
+    if central.uncompressed_size != uncompressed_size {
+        mismatches.push(HeaderMismatch::UncompressedSize {
+            central: central.uncompressed_size,
+            local: uncompressed_size,
+        });
+    }
+
+    mismatches
+}
@@ -2,24 +2,45 @@
 // https://pkware.cachefly.net/webdocs/APPNOTE/APPNOTE-6.3.9.TXT
 // https://users.cs.jmu.edu/buchhofp/forensics/formats/pkzip.html
 
-#![feature(iter_array_chunks)]
 #![allow(dead_code)]
 #![deny(clippy::unwrap_used, clippy::expect_used)]
 
-use std::{io::SeekFrom, path::Path};
-
-use tokio::{
-    fs::{self, File},
-    io::{AsyncReadExt, AsyncSeekExt},
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
 };
 
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
 mod compression;
+mod crypto;
+mod entry;
 mod error;
+mod extract;
 mod header;
+mod integrity;
+mod limits;
+mod recover;
+mod sequential;
+mod text;
+mod time;
+mod volume;
+mod writer;
 
 pub use compression::CompressionType;
+pub use encoding_rs;
+pub use entry::EntryReader;
 pub use error::*;
+pub use extract::*;
+pub use header::EntryKind;
 pub(crate) use header::*;
+pub use integrity::*;
+pub use limits::ArchiveLimits;
+pub use recover::*;
+pub use sequential::*;
+pub use time::*;
+pub use volume::ArchiveFile;
+pub use writer::*;
 
 /// Buffer Read Size
 const BUFFER_SIZE: usize = 1000;
@@ -27,8 +48,21 @@ const BUFFER_SIZE: usize = 1000;
 /// Signature takes up 4 bytes.
 const SIGNATURE_SIZE: usize = 4;
 
+/// Options used when reading an archive.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ArchiveOptions {
+    /// Decodes the names and comments which aren't flagged as UTF-8, instead of CP437.
+    ///
+    /// e.g. Shift_JIS for archives made on a Japanese version of Windows.
+    pub fallback_encoding: Option<&'static encoding_rs::Encoding>,
+    /// Protects against zip bombs. Nothing is limited by default.
+    pub limits: ArchiveLimits,
+}
+
 pub struct Archive {
-    file: File,
+    file: ArchiveFile,
+
+    options: ArchiveOptions,
 
     file_cache: FileReaderCache,
 
@@ -37,8 +71,33 @@ pub struct Archive {
 
 impl Archive {
     pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_options(path, ArchiveOptions::default()).await
+    }
+
+    pub async fn open_with_options(
+        path: impl AsRef<Path>,
+        options: ArchiveOptions,
+    ) -> Result<Self> {
+        Self::from_file(ArchiveFile::open(path).await?, options).await
+    }
+
+    /// Open a split archive. See [`ArchiveFile::open_split`] for how the other volumes are found.
+    pub async fn open_split(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_split_with_options(path, ArchiveOptions::default()).await
+    }
+
+    pub async fn open_split_with_options(
+        path: impl AsRef<Path>,
+        options: ArchiveOptions,
+    ) -> Result<Self> {
+        Self::from_file(ArchiveFile::open_split(path).await?, options).await
+    }
+
+    async fn from_file(file: ArchiveFile, options: ArchiveOptions) -> Result<Self> {
         let mut this = Self {
-            file: fs::OpenOptions::new().read(true).open(path).await?,
+            file,
+
+            options,
 
             file_cache: FileReaderCache::default(),
             end_header: EndCentralDirHeader::default(),
@@ -46,10 +105,12 @@ impl Archive {
 
         this.parse().await?;
 
-        // TODO: Move out. Capacity reserve is used to tell us how many files we have for when we iterate through.
-        this.file_cache
-            .files
-            .reserve(this.end_header.total_record_count as usize);
+        let central_dir_offset = this.file.disk_position(
+            this.end_header.start_disk_number,
+            this.end_header.curr_offset,
+        )?;
+
+        this.file_cache = FileReaderCache::new(&this.end_header, central_dir_offset, options);
 
         Ok(this)
     }
@@ -84,7 +145,24 @@ impl Archive {
     async fn parse(&mut self) -> Result<()> {
         let mut reader = ArchiveReader::init(&mut self.file).await?;
 
-        self.end_header = EndCentralDirHeader::find(&mut reader).await?;
+        self.end_header =
+            EndCentralDirHeader::find(&mut reader, self.options.fallback_encoding).await?;
+
+        // The End of Central Directory record is on the last disk. 7-Zip style volumes all say disk 0.
+        let last_disk = self.end_header.current_disk_number;
+        let volumes = self.file.volume_count();
+
+        if last_disk != 0 && last_disk as usize + 1 != volumes {
+            return Err(Error::VolumeCountMismatch {
+                expected: last_disk as u64 + 1,
+                found: volumes as u64,
+            });
+        }
+
+        // Before anything is allocated for the records.
+        self.options
+            .limits
+            .check_entry_count(self.end_header.total_record_count)?;
 
         // A directory is placed at the end of a ZIP file. This identifies what files are in the ZIP and identifies where in the ZIP that file is located.
         // A ZIP file is correctly identified by the presence of an end of central directory record which is located at the end of the archive structure in order to allow the easy appending of new files.
@@ -96,14 +174,14 @@ impl Archive {
 
 pub struct ArchiveReader<'a> {
     // TODO: Utilize BufReader
-    file: &'a mut File,
+    file: &'a mut ArchiveFile,
 
     index: usize,
     last_read_amount: usize,
 }
 
 impl<'a> ArchiveReader<'a> {
-    pub async fn init(file: &'a mut File) -> Result<ArchiveReader<'a>> {
+    pub async fn init(file: &'a mut ArchiveFile) -> Result<ArchiveReader<'a>> {
         // Seek back to start.
         file.seek(SeekFrom::Start(0)).await?;
 
@@ -135,8 +213,22 @@ impl<'a> ArchiveReader<'a> {
     }
 
     /// Repopulate buffer with next data. read also updates seek position.
+    ///
+    /// Reads stop at the end of each volume of a split archive, so keep going until it's full.
     async fn seek_next(&mut self, buffer: &mut [u8; BUFFER_SIZE]) -> Result<()> {
-        self.last_read_amount = self.file.read(buffer).await?;
+        let mut filled = 0;
+
+        while filled < buffer.len() {
+            let amount = self.file.read(&mut buffer[filled..]).await?;
+
+            if amount == 0 {
+                break;
+            }
+
+            filled += amount;
+        }
+
+        self.last_read_amount = filled;
         self.index = 0;
 
         Ok(())
@@ -169,61 +261,88 @@ impl<'a> ArchiveReader<'a> {
         Ok(v)
     }
 
+    /// Read `size` bytes, which can run over several buffers. Fails if the archive ends first.
     async fn get_chunk_amount(
         &mut self,
         buffer: &mut [u8; BUFFER_SIZE],
         mut size: usize,
     ) -> Result<Vec<u8>> {
-        let mut filled = Vec::with_capacity(size);
+        // The size comes from the archive, so don't reserve more than a buffer up front.
+        let mut filled = Vec::with_capacity(size.min(BUFFER_SIZE));
 
         while size != 0 {
-            if self.index + size >= buffer.len() {
-                // trace!("idx {}, size {} >= buffer {}", self.index, size, buffer.len());
-                filled.extend_from_slice(&buffer[self.index..]);
-
-                size -= buffer.len() - self.index;
+            // Only the start of the buffer was filled by the last read.
+            let available = self.last_read_amount.saturating_sub(self.index);
 
-                self.seek_next(buffer).await?;
-            } else {
+            if size < available {
                 filled.extend_from_slice(&buffer[self.index..self.index + size]);
                 self.index += size;
 
                 break;
             }
+
+            filled.extend_from_slice(&buffer[self.index..self.index + available]);
+            size -= available;
+
+            self.seek_next(buffer).await?;
+
+            if size != 0 && self.last_read_amount == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
         }
 
         Ok(filled)
     }
 
-    fn find_next_signature<const SIG_SIZE: usize>(
-        &self,
-        buffer: &[u8],
-        signature: [u8; SIG_SIZE],
-    ) -> Option<usize> {
-        buffer[self.index..]
-            .windows(SIG_SIZE)
-            .position(|v| v == signature)
-            .map(|offset| self.index + offset)
-    }
-
     async fn next_u16(&mut self, buffer: &mut [u8; BUFFER_SIZE]) -> Result<u16> {
         let buf = self.get_next_chunk::<2>(buffer).await?;
 
-        Ok((buf[1] as u16) << 8 | buf[0] as u16)
+        Ok(bytes_to_u16(buf))
     }
 
     async fn next_u32(&mut self, buffer: &mut [u8; BUFFER_SIZE]) -> Result<u32> {
         let buf = self.get_next_chunk::<4>(buffer).await?;
 
-        Ok((buf[3] as u32) << 24 | (buf[2] as u32) << 16 | (buf[1] as u32) << 8 | buf[0] as u32)
+        Ok(bytes_to_u32(buf))
+    }
+
+    async fn next_u64(&mut self, buffer: &mut [u8; BUFFER_SIZE]) -> Result<u64> {
+        let buf = self.get_next_chunk::<8>(buffer).await?;
+
+        Ok(bytes_to_u64(buf))
     }
 }
 
+pub(crate) fn bytes_to_u64(bytes: &[u8]) -> u64 {
+    assert!(bytes.len() == 8);
+
+    (bytes[7] as u64) << 56
+        | (bytes[6] as u64) << 48
+        | (bytes[5] as u64) << 40
+        | (bytes[4] as u64) << 32
+        | (bytes[3] as u64) << 24
+        | (bytes[2] as u64) << 16
+        | (bytes[1] as u64) << 8
+        | bytes[0] as u64
+}
+
+pub(crate) fn bytes_to_u32(bytes: &[u8]) -> u32 {
+    assert!(bytes.len() == 4);
+
+    (bytes[3] as u32) << 24 | (bytes[2] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[0] as u32
+}
+
+pub(crate) fn bytes_to_u16(bytes: &[u8]) -> u16 {
+    assert!(bytes.len() == 2);
+
+    (bytes[1] as u16) << 8 | bytes[0] as u16
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::expect_used)]
 
-    use tokio::runtime::Runtime;
+    use tokio::{fs, runtime::Runtime};
 
     use super::*;
 
@@ -232,9 +351,10 @@ mod tests {
         let rt = Runtime::new()?;
 
         rt.block_on(async {
-            let mut archive = Archive::open("./resources/Zip Test 7-Zip.zip").await?;
+            let mut archive = Archive::open("../../resources/zip/Zip Test 7-Zip.zip").await?;
 
-            //
+            assert!(!archive.info().is_zip64);
+            assert_eq!(archive.list_files().await?.len(), 5);
 
             Result::<_, Error>::Ok(())
         })?;
@@ -247,9 +367,2415 @@ mod tests {
         let rt = Runtime::new()?;
 
         rt.block_on(async {
-            let mut archive = Archive::open("./resources/Zip Test Windows.zip").await?;
+            let mut archive = Archive::open("../../resources/zip/Zip Test Windows.zip").await?;
+
+            assert_eq!(archive.list_files().await?.len(), 3);
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_zip64() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Python Zip64.zip").await?;
+
+            let info = archive.info();
+
+            assert!(info.is_zip64);
+            assert_eq!(info.records, 5);
+
+            let files = archive.list_files().await?;
+
+            assert_eq!(files.len(), 5);
+
+            let ipsum = &files[4];
+
+            assert_eq!(ipsum.file_name, "Large Files/ipsum 2.txt");
+            assert_eq!(ipsum.compressed_size, 10090);
+            assert_eq!(ipsum.uncompressed_size, 10090);
+            assert_eq!(ipsum.relative_offset, 6925);
+
+            let bytes = fs::read("../../resources/zip/Zip Test Python Zip64.zip").await?;
+            let path = temp_path("zip64-damaged.zip");
+
+            // A record size which would have the extensible data run past the locator.
+            let mut damaged = bytes.clone();
+            let record = damaged
+                .windows(SIGNATURE_SIZE)
+                .rposition(|v| v == ZIP64_END_CENTRAL_DIR_SIG)
+                .expect("Zip64 End Header");
+
+            damaged[record + 4..record + 12].copy_from_slice(&u64::MAX.to_le_bytes());
+            fs::write(&path, &damaged).await?;
+
+            assert!(matches!(
+                Archive::open(&path).await,
+                Err(Error::Zip64EndHeaderOverrun { available: 0, .. })
+            ));
+
+            // A Zip64 compressed size which would overflow, then one which runs past the end of the file.
+            let name = b"Large Files/ipsum 2.txt";
+            let compressed_size = bytes
+                .windows(name.len())
+                .rposition(|v| v == name)
+                .expect("Central Directory record")
+                + name.len()
+                + 12;
+
+            for size in [u64::MAX - 100, bytes.len() as u64] {
+                let mut damaged = bytes.clone();
+                damaged[compressed_size..compressed_size + 8].copy_from_slice(&size.to_le_bytes());
+                fs::write(&path, &damaged).await?;
+
+                let report = Archive::open(&path).await?.test().await?;
+
+                assert!(matches!(
+                    report.entries[4].error,
+                    Some(Error::EntryOverrun { offset: 6925, end }) if end == bytes.len() as u64
+                ));
+            }
+
+            // A file name in the Local File Header which runs past the end of the file.
+            let mut damaged = bytes;
+            damaged[6925 + 26..6925 + 28].copy_from_slice(&u16::MAX.to_le_bytes());
+            fs::write(&path, &damaged).await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+
+            assert!(matches!(
+                files[4].read(&mut archive).await,
+                Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+            ));
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_split() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test Info-ZIP Split.zip";
+            let appnote = fs::read("../../resources/zip/APPNOTE-6.3.10.TXT").await?;
+
+            let mut archive = Archive::open_split(path).await?;
+
+            assert!(archive.info().is_multi_disk);
+
+            let files = archive.list_files().await?;
+
+            let disks = files
+                .iter()
+                .map(|v| v.current_disk_number)
+                .collect::<Vec<_>>();
+
+            assert_eq!(disks, [0, 2, 2, 2, 2]);
+
+            // Crosses from the first volume into the second and third.
+            assert_eq!(files[0].file_name, "APPNOTE-6.3.10.TXT");
+            assert_eq!(files[0].read(&mut archive).await?, appnote);
+
+            for file in &files[1..] {
+                let contents = file.read(&mut archive).await?;
+
+                assert_eq!(contents.len() as u64, file.uncompressed_size);
+            }
+
+            assert_eq!(
+                files[1].read(&mut archive).await?,
+                fs::read("../../resources/unzipped contents/File 2.txt").await?
+            );
+
+            // Starts after the spanning signature.
+            let mut reader = SequentialReader::open_split(path).await?;
+            let mut names = Vec::new();
+
+            while let Some(entry) = reader.next_entry().await? {
+                names.push(entry.header().file_name.clone());
+
+                entry.read().await?;
+            }
+
+            assert_eq!(names.len(), 5);
+            assert_eq!(names[0], "APPNOTE-6.3.10.TXT");
+
+            // Only the last volume.
+            assert!(matches!(
+                Archive::open(path).await,
+                Err(Error::VolumeCountMismatch {
+                    expected: 3,
+                    found: 1
+                })
+            ));
+
+            // 7-Zip cuts the archive into pieces instead, which all say they're disk 0.
+            let mut archive =
+                Archive::open_split("../../resources/zip/Zip Test 7-Zip Deflate Fastest Split.001")
+                    .await?;
+
+            assert!(!archive.info().is_multi_disk);
+
+            for file in archive.list_files().await? {
+                let contents = file.read(&mut archive).await?;
+
+                assert_eq!(contents.len() as u64, file.uncompressed_size);
+            }
+
+            // Cut inside the second Local File Header, so reading it has to carry on into the next piece.
+            let bytes = tokio::fs::read("../../resources/zip/Zip Test Info-ZIP.zip").await?;
+            let split = temp_path("split-header.zip");
+            let first = split.with_extension("zip.001");
+            let second = split.with_extension("zip.002");
+
+            tokio::fs::write(&first, &bytes[..105]).await?;
+            tokio::fs::write(&second, &bytes[105..]).await?;
+
+            let mut archive = Archive::open_split(&first).await?;
+            let files = archive.list_files().await?;
+
+            assert_eq!(files.len(), 2);
+            assert_eq!(files[1].relative_offset, 95);
+
+            for file in files {
+                let contents = file.read(&mut archive).await?;
+
+                assert_eq!(contents.len() as u64, file.uncompressed_size);
+            }
+
+            tokio::fs::remove_file(first).await?;
+            tokio::fs::remove_file(second).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    async fn assert_files_read(path: &str) -> Result<()> {
+        let mut archive = Archive::open(path).await?;
+
+        for file in archive.list_files().await? {
+            let contents = file.read(&mut archive).await?;
+
+            assert_eq!(
+                contents.len() as u64,
+                file.uncompressed_size,
+                "{}",
+                file.file_name
+            );
+
+            if file.file_name == "File 2.txt" {
+                assert_eq!(
+                    contents,
+                    fs::read("../../resources/unzipped contents/File 2.txt").await?
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_read_stored() -> Result<(), Error> {
+        Runtime::new()?.block_on(assert_files_read("../../resources/zip/Zip Test 7-Zip.zip"))
+    }
+
+    #[test]
+    fn zip_read_deflate() -> Result<(), Error> {
+        Runtime::new()?.block_on(assert_files_read(
+            "../../resources/zip/Zip Test 7-Zip Deflate Ultra.zip",
+        ))
+    }
+
+    #[test]
+    fn zip_read_bzip2() -> Result<(), Error> {
+        Runtime::new()?.block_on(assert_files_read(
+            "../../resources/zip/Zip Test 7-Zip BZip2 Ultra.zip",
+        ))
+    }
+
+    #[test]
+    fn zip_read_lzma() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            // With end of stream markers.
+            assert_files_read("../../resources/zip/Zip Test 7-Zip LZMA Ultra.zip").await?;
+
+            let path = "../../resources/zip/Zip Test LZMA.zip";
+
+            // Central Directory
+            assert_files_read(path).await?;
+
+            // Sequential
+            let mut reader = SequentialReader::open(path).await?;
+            let mut sizes = Vec::new();
+
+            while let Some(entry) = reader.next_entry().await? {
+                sizes.push(entry.read().await?.len());
+            }
+
+            assert_eq!(sizes, [27, 6665, 10090]);
+
+            // Streamed, so only the end of stream marker says where the data ends.
+            let streamed = "../../resources/zip/Zip Test LZMA Streamed.zip";
+
+            assert_files_read(streamed).await?;
+
+            let mut reader = SequentialReader::open(streamed).await?;
+            let mut sizes = Vec::new();
+
+            while let Some(mut entry) = reader.next_entry().await? {
+                assert_eq!(entry.header().compressed_size, 0);
+
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).await?;
+
+                let descriptor = entry.finish().await?.expect("data descriptor");
+
+                assert_eq!(descriptor.uncompressed_size, contents.len() as u64);
+
+                sizes.push(contents.len());
+            }
+
+            assert_eq!(sizes, [27, 6665]);
+
+            let (mut archive, report) = Archive::recover(streamed).await?;
+
+            assert!(report.is_intact());
+
+            for file in archive.list_files().await? {
+                assert_eq!(
+                    file.read(&mut archive).await?.len() as u64,
+                    file.uncompressed_size
+                );
+            }
+
+            // The dictionary is 64 MiB.
+            let options = ArchiveOptions {
+                limits: ArchiveLimits {
+                    max_decoder_memory: Some(64 * 1024),
+                    ..ArchiveLimits::default()
+                },
+                ..ArchiveOptions::default()
+            };
+
+            let mut reader = SequentialReader::open_with_options(path, options).await?;
+            let entry = reader.next_entry().await?.expect("File 2.txt");
+
+            assert!(matches!(
+                entry.read().await,
+                Err(Error::DecoderMemoryExceeded {
+                    limit: 65536,
+                    found: Some(67108864)
+                })
+            ));
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "zstd", feature = "xz"))]
+    #[test]
+    fn zip_read_zstd_xz() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test Zstd Xz.zip";
+
+            assert_files_read(path).await?;
+
+            // The window and dictionary are 8 MiB.
+            let options = ArchiveOptions {
+                limits: ArchiveLimits {
+                    max_decoder_memory: Some(1024 * 1024),
+                    ..ArchiveLimits::default()
+                },
+                ..ArchiveOptions::default()
+            };
+
+            let mut archive = Archive::open_with_options(path, options).await?;
+            let files = archive.list_files().await?;
+
+            assert!(matches!(
+                files[0].read(&mut archive).await,
+                Err(Error::DecoderMemoryExceeded {
+                    limit: 1048576,
+                    found: Some(8388608)
+                })
+            ));
+            assert!(matches!(
+                files[2].read(&mut archive).await,
+                Err(Error::DecoderMemoryExceeded {
+                    limit: 1048576,
+                    found: None
+                })
+            ));
+
+            Result::<_, Error>::Ok(())
+        })
+    }
+
+    #[test]
+    fn zip_read_legacy() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test Legacy.zip";
+
+            assert_files_read(path).await?;
+
+            let mut archive = Archive::open(path).await?;
+            let files = archive.list_files().await?;
+
+            let compression = files.iter().map(|v| v.compression).collect::<Vec<_>>();
+
+            assert_eq!(
+                compression,
+                [
+                    CompressionType::Shrunk,
+                    CompressionType::Shrunk,
+                    CompressionType::ReducedCF1,
+                    CompressionType::ReducedCF4,
+                    CompressionType::Imploded,
+                    CompressionType::Imploded,
+                ]
+            );
+
+            assert_eq!(
+                files[0].read(&mut archive).await?,
+                fs::read("../../resources/unzipped contents/File 2.txt").await?
+            );
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_read_deflate64() -> Result<(), Error> {
+        Runtime::new()?.block_on(assert_files_read(
+            "../../resources/zip/Zip Test Deflate64.zip",
+        ))
+    }
+
+    #[test]
+    fn zip_read_ppmd() -> Result<(), Error> {
+        Runtime::new()?.block_on(assert_files_read("../../resources/zip/Zip Test PPMd.zip"))
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("zip-archiver-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn zip_checksum_mismatch() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let source = "../../resources/zip/Zip Test 7-Zip.zip";
+
+            let mut archive = Archive::open(source).await?;
+            let files = archive.list_files().await?;
+            let file = files
+                .iter()
+                .find(|v| v.file_name == "File 2.txt")
+                .expect("File 2.txt");
+
+            // Flip a byte of the stored data.
+            let mut contents = fs::read(source).await?;
+            let offset = file.relative_offset as usize;
+            let extra_len = bytes_to_u16(&contents[offset + 28..offset + 30]) as usize;
+            contents[offset + 30 + file.file_name.len() + extra_len] ^= 0xFF;
+
+            let path = temp_path("checksum.zip");
+            fs::write(&path, contents).await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+            let file = files
+                .iter()
+                .find(|v| v.file_name == "File 2.txt")
+                .expect("File 2.txt");
+
+            let res = file.read(&mut archive).await;
+
+            fs::remove_file(&path).await?;
+
+            assert!(matches!(
+                res,
+                Err(Error::ChecksumMismatch {
+                    expected: 0xE1362DB1,
+                    ..
+                })
+            ));
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_data_descriptor() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test Python Data Descriptor.zip";
+
+            // Central Directory
+            assert_files_read(path).await?;
+
+            // Sequential
+            let mut reader = SequentialReader::open(path).await?;
+            let mut names = Vec::new();
+
+            while let Some(mut entry) = reader.next_entry().await? {
+                let header = entry.header().clone();
+
+                assert!(header.has_data_descriptor());
+                assert_eq!(header.compressed_size, 0);
+
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).await?;
+
+                let descriptor = entry.finish().await?.expect("data descriptor");
+
+                assert_eq!(descriptor.uncompressed_size, contents.len() as u64);
+                assert_eq!(
+                    header.is_zip64,
+                    header.file_name == "Large Files/ipsum 2.txt"
+                );
+
+                names.push(header.file_name);
+            }
+
+            assert_eq!(names.len(), 4);
+
+            // Skipping entries without reading them.
+            let mut reader = SequentialReader::open(path).await?;
+            let mut count = 0;
+
+            while reader.next_entry().await?.is_some() {
+                count += 1;
+            }
+
+            assert_eq!(count, 4);
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_recover() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let source = "../../resources/zip/Zip Test Python Data Descriptor.zip";
+
+            let mut archive = Archive::open(source).await?;
+            let expected = archive.list_files().await?;
+            let central_dir_size = archive.info().central_dir_size as usize;
+
+            let contents = fs::read(source).await?;
+
+            // Everything after the entries is lost.
+            let entries_end = contents.len() - END_CENTRAL_DIR_SIZE_KNOWN - central_dir_size;
+
+            let path = temp_path("recover.zip");
+            fs::write(&path, &contents[..entries_end]).await?;
+
+            assert!(matches!(
+                Archive::open(&path).await,
+                Err(Error::MissingEndHeader)
+            ));
+
+            let (mut archive, report) = Archive::recover(&path).await?;
+
+            assert!(report.is_intact());
+            assert_eq!(report.recovered, 4);
+
+            for (file, expected) in archive.list_files().await?.iter().zip(&expected) {
+                assert_eq!(file.file_name, expected.file_name);
+                assert_eq!(file.crc_32, expected.crc_32);
+                assert_eq!(file.compressed_size, expected.compressed_size);
+                assert_eq!(file.uncompressed_size, expected.uncompressed_size);
+                assert_eq!(file.relative_offset, expected.relative_offset);
+
+                assert_eq!(
+                    file.read(&mut archive).await?,
+                    expected.read(&mut Archive::open(source).await?).await?
+                );
+            }
+
+            // Damage "Large Files/ipsum 1.txt" and cut "Large Files/ipsum 2.txt" short.
+            let mut damaged = contents[..expected[3].relative_offset as usize + 200].to_vec();
+            damaged[expected[2].relative_offset as usize + 100] ^= 0xFF;
+
+            fs::write(&path, damaged).await?;
+
+            let (mut archive, report) = Archive::recover(&path).await?;
+
+            let names = archive
+                .list_files()
+                .await?
+                .into_iter()
+                .map(|v| v.file_name)
+                .collect::<Vec<_>>();
+
+            assert_eq!(names, ["File 1.txt", "File 2.txt"]);
+
+            let damaged = report
+                .damaged
+                .iter()
+                .map(|v| (v.offset, v.file_name.as_deref()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                damaged,
+                [
+                    (expected[2].relative_offset, Some("Large Files/ipsum 1.txt")),
+                    (expected[3].relative_offset, Some("Large Files/ipsum 2.txt")),
+                ]
+            );
+
+            // Encrypted entries with a data descriptor are found by searching for it.
+            let source = "../../resources/zip/Zip Test Info-ZIP ZipCrypto Streamed.zip";
+            let contents = fs::read(source).await?;
+            let central_dir_size = Archive::open(source).await?.info().central_dir_size as usize;
+
+            fs::write(
+                &path,
+                &contents[..contents.len() - END_CENTRAL_DIR_SIZE_KNOWN - central_dir_size],
+            )
+            .await?;
+
+            let (mut archive, report) = Archive::recover(&path).await?;
+
+            fs::remove_file(&path).await?;
+
+            assert!(report.is_intact());
+
+            let files = archive.list_files().await?;
+
+            assert_eq!(files.len(), 2);
+            assert_eq!(
+                files[0]
+                    .read_with_password(&mut archive, "archiver")
+                    .await?,
+                fs::read("../../resources/unzipped contents/File 2.txt").await?
+            );
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_zip_crypto() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            for path in [
+                "../../resources/zip/Zip Test Info-ZIP ZipCrypto.zip",
+                "../../resources/zip/Zip Test Info-ZIP ZipCrypto Streamed.zip",
+                "../../resources/zip/Zip Test Python ZipCrypto.zip",
+            ] {
+                let mut archive = Archive::open(path).await?;
+
+                for file in archive.list_files().await? {
+                    assert!(file.is_encrypted());
+
+                    assert!(matches!(
+                        file.read(&mut archive).await,
+                        Err(Error::PasswordRequired)
+                    ));
+
+                    assert!(matches!(
+                        file.read_with_password(&mut archive, "wrong").await,
+                        Err(Error::InvalidPassword)
+                    ));
+
+                    let contents = file.read_with_password(&mut archive, "archiver").await?;
+
+                    assert_eq!(contents.len() as u64, file.uncompressed_size, "{path}");
+
+                    if file.file_name == "File 2.txt" {
+                        assert_eq!(
+                            contents,
+                            fs::read("../../resources/unzipped contents/File 2.txt").await?
+                        );
+                    }
+                }
+
+                // Sequential, where the streamed entries are only followed by their data descriptor.
+                let expected = archive
+                    .list_files()
+                    .await?
+                    .iter()
+                    .map(|v| v.file_name.clone())
+                    .collect::<Vec<_>>();
+
+                let mut reader = SequentialReader::open(path).await?;
+                let mut skipped = 0;
+
+                loop {
+                    match reader.next_entry().await {
+                        Err(Error::PasswordRequired) => skipped += 1,
+                        Ok(None) => break,
+                        Ok(Some(_)) => panic!("{path}: entry isn't encrypted"),
+                        Err(e) => return Err(e),
+                    }
+                }
+
+                assert_eq!(skipped, expected.len(), "{path}");
+
+                let mut reader = SequentialReader::open(path).await?;
+
+                assert!(matches!(
+                    reader.next_entry_with_password("wrong").await,
+                    Err(Error::InvalidPassword)
+                ));
+
+                let mut reader = SequentialReader::open(path).await?;
+                let mut names = Vec::new();
+
+                while let Some(entry) = reader.next_entry_with_password("archiver").await? {
+                    let name = entry.header().file_name.clone();
+                    let contents = entry.read().await?;
+
+                    if name == "File 2.txt" {
+                        assert_eq!(
+                            contents,
+                            fs::read("../../resources/unzipped contents/File 2.txt").await?
+                        );
+                    }
+
+                    names.push(name);
+                }
+
+                assert_eq!(names, expected, "{path}");
+            }
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_winzip_aes() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test WinZip AES.zip";
+
+            let mut archive = Archive::open(path).await?;
+            let files = archive.list_files().await?;
+
+            assert_eq!(files.len(), 3);
+
+            for file in files {
+                assert_eq!(file.compression, CompressionType::Aex);
+
+                let aes = file.aes().expect("AES extra field");
+
+                assert!(matches!(
+                    file.read_with_password(&mut archive, "wrong").await,
+                    Err(Error::InvalidPassword)
+                ));
+
+                let contents = file.read_with_password(&mut archive, "archiver").await?;
+
+                assert_eq!(contents.len() as u64, file.uncompressed_size);
+
+                if file.file_name == "File 2.txt" {
+                    assert_eq!(aes.compression, CompressionType::None);
+                    assert_eq!(
+                        contents,
+                        fs::read("../../resources/unzipped contents/File 2.txt").await?
+                    );
+                } else {
+                    assert_eq!(aes.compression, CompressionType::Deflate);
+                }
+            }
+
+            // Sequential
+            let mut reader = SequentialReader::open(path).await?;
+            let mut count = 0;
+
+            while let Some(entry) = reader.next_entry_with_password("archiver").await? {
+                let name = entry.header().file_name.clone();
+                let contents = entry.read().await?;
+
+                if name == "File 2.txt" {
+                    assert_eq!(
+                        contents,
+                        fs::read("../../resources/unzipped contents/File 2.txt").await?
+                    );
+                }
+
+                count += 1;
+            }
+
+            assert_eq!(count, 3);
+
+            // Tampered data fails the authentication code check.
+            let mut bytes = fs::read(path).await?;
+            let tampered = temp_path("aes-tampered.zip");
+
+            let file = &Archive::open(path).await?.list_files().await?[0];
+            let data_end = file.relative_offset as usize
+                + 30
+                + file.file_name.len()
+                + 11
+                + file.compressed_size as usize
+                - 10;
+
+            bytes[data_end - 1] ^= 0xFF;
+            fs::write(&tampered, bytes).await?;
+
+            let mut archive = Archive::open(&tampered).await?;
+            let file = &archive.list_files().await?[0];
+
+            let result = file.read_with_password(&mut archive, "archiver").await;
+
+            fs::remove_file(&tampered).await?;
+
+            assert!(matches!(result, Err(Error::AuthenticationMismatch)));
+
+            // Method 99 without the AES extra field isn't mistaken for ZipCrypto.
+            let bytes = fs::read(path).await?;
+            let field = [0x01, 0x99, 0x07, 0x00];
+            let mut damaged = bytes.clone();
+
+            for index in 0..bytes.len() - field.len() {
+                if bytes[index..index + field.len()] == field {
+                    damaged[index] = 0xFF;
+                }
+            }
+
+            let path = temp_path("aes-missing.zip");
+            fs::write(&path, damaged).await?;
+
+            let mut archive = Archive::open(&path).await?;
+
+            for file in archive.list_files().await? {
+                assert!(matches!(
+                    file.read_with_password(&mut archive, "archiver").await,
+                    Err(Error::MissingAesExtraField)
+                ));
+            }
+
+            let mut reader = SequentialReader::open(&path).await?;
+
+            assert!(matches!(
+                reader.next_entry_with_password("archiver").await,
+                Err(Error::MissingAesExtraField)
+            ));
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_writer() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("writer.zip");
+            let ipsum =
+                fs::read("../../resources/unzipped contents/Large Files/ipsum 1.txt").await?;
+
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer
+                .add_bytes("stored.txt", b"Stored contents", EntryOptions::stored())
+                .await?;
+            writer
+                .add_bytes("fast.txt", &ipsum, EntryOptions::deflated(1))
+                .await?;
+            writer
+                .add_path(
+                    "Large Files/ipsum 1.txt",
+                    "../../resources/unzipped contents/Large Files/ipsum 1.txt",
+                    EntryOptions::deflated(9),
+                )
+                .await?;
+            writer
+                .add_reader("reader.txt", &ipsum[..], EntryOptions::default())
+                .await?;
+            writer
+                .add_directory("Empty Folder", EntryOptions::default())
+                .await?;
+
+            // Large enough to be written before the Local File Header is filled in.
+            let large = ipsum.repeat(64);
+
+            writer
+                .add_reader("large.txt", &large[..], EntryOptions::stored())
+                .await?;
+
+            // Lengths which don't fit in the headers are rejected before anything is written.
+            let long = "a".repeat(u16::MAX as usize + 1);
+
+            assert!(matches!(
+                writer
+                    .add_bytes(long.as_str(), b"", EntryOptions::default())
+                    .await,
+                Err(Error::HeaderFieldTooLong {
+                    field: "file name",
+                    found: 65536
+                })
+            ));
+            assert!(matches!(
+                writer.set_comment(long.as_str()),
+                Err(Error::HeaderFieldTooLong {
+                    field: "archive comment",
+                    ..
+                })
+            ));
+
+            writer.set_comment("Written by zip-archiver")?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+
+            let info = archive.info();
+
+            assert!(!info.is_zip64);
+            assert_eq!(info.comment, "Written by zip-archiver");
+
+            let files = archive.list_files().await?;
+
+            let names = files
+                .iter()
+                .map(|v| v.file_name.as_str())
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                names,
+                [
+                    "stored.txt",
+                    "fast.txt",
+                    "Large Files/ipsum 1.txt",
+                    "reader.txt",
+                    "Empty Folder/",
+                    "large.txt"
+                ]
+            );
+
+            assert_eq!(files[0].compression, CompressionType::None);
+            assert_eq!(files[0].read(&mut archive).await?, b"Stored contents");
+
+            for file in &files[1..4] {
+                assert_eq!(file.compression, CompressionType::Deflate);
+                assert!(file.compressed_size < file.uncompressed_size);
+                assert_eq!(file.read(&mut archive).await?, ipsum);
+            }
+
+            assert_eq!(files[4].uncompressed_size, 0);
+            assert_eq!(files[4].external_file_attr, 0x10);
+
+            assert_eq!(files[5].compressed_size, large.len() as u64);
+            assert_eq!(files[5].read(&mut archive).await?, large);
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_end_header_search() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("end-header-search.zip");
+
+            // An empty archive's End of Central Directory record, which a forward search would find first.
+            let mut fake = b"PK\x05\x06".to_vec();
+            fake.resize(END_CENTRAL_DIR_SIZE_KNOWN, 0);
+
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer
+                .add_bytes("padding.bin", vec![0; 70_000], EntryOptions::stored())
+                .await?;
+            writer
+                .add_bytes(
+                    "fake entry with a long name.bin",
+                    &fake,
+                    EntryOptions::stored(),
+                )
+                .await?;
+
+            // The comment length in here runs past the end of the file.
+            writer.set_comment("PK\u{5}\u{6}\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\u{7f}\0")?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+
+            assert_eq!(archive.info().records, 2);
+            assert!(archive.info().comment.starts_with("PK"));
+            assert_eq!(archive.list_files().await?.len(), 2);
+
+            fs::write(&path, b"PK\x05\x06").await?;
+
+            assert!(matches!(
+                Archive::open(&path).await,
+                Err(Error::MissingEndHeader)
+            ));
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_central_directory_records() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("central-directory-records.zip");
+
+            // A signature scan would pick this up as a record. The long names leave room in the Central Directory
+            // for the extra record count below to still pass as a valid End of Central Directory record.
+            let mut fake = b"PK\x01\x02".to_vec();
+            fake.resize(CENTRAL_DIR_SIZE_KNOWN, 0);
+
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer
+                .add_bytes(
+                    "fake entry with a long name.bin",
+                    &fake,
+                    EntryOptions::stored(),
+                )
+                .await?;
+            writer
+                .add_bytes(
+                    "real entry with a long name.txt",
+                    b"Real contents",
+                    EntryOptions::stored(),
+                )
+                .await?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+
+            let names = archive
+                .list_files()
+                .await?
+                .into_iter()
+                .map(|v| v.file_name)
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                names,
+                [
+                    "fake entry with a long name.bin",
+                    "real entry with a long name.txt"
+                ]
+            );
+
+            let contents = fs::read(&path).await?;
+            let end = contents.len() - END_CENTRAL_DIR_SIZE_KNOWN;
+
+            // Overwrite the record counts and the Central Directory size in the End of Central Directory record.
+            let with_counts = |records: u16, size_adjust: i64| {
+                let mut contents = contents.clone();
+                let size = bytes_to_u32(&contents[end + 12..end + 16]) as i64 + size_adjust;
+
+                contents[end + 8..end + 10].copy_from_slice(&records.to_le_bytes());
+                contents[end + 10..end + 12].copy_from_slice(&records.to_le_bytes());
+                contents[end + 12..end + 16].copy_from_slice(&(size as u32).to_le_bytes());
+
+                contents
+            };
+
+            fs::write(&path, with_counts(3, 0)).await?;
+
+            assert!(matches!(
+                Archive::open(&path).await?.list_files().await,
+                Err(Error::RecordCountMismatch {
+                    expected: 3,
+                    found: 2
+                })
+            ));
+
+            fs::write(&path, with_counts(1, 0)).await?;
+
+            assert!(matches!(
+                Archive::open(&path).await?.list_files().await,
+                Err(Error::CentralDirSizeMismatch { .. })
+            ));
+
+            fs::write(&path, with_counts(2, -1)).await?;
+
+            assert!(matches!(
+                Archive::open(&path).await?.list_files().await,
+                Err(Error::CentralDirOverrun { .. })
+            ));
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_writer_zip64() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("writer-zip64.zip");
+
+            // More entries than the End of Central Directory record can hold.
+            let count = u16::MAX as usize + 10;
+
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            for i in 0..count {
+                writer
+                    .add_bytes(format!("{i}.txt"), i.to_string(), EntryOptions::stored())
+                    .await?;
+            }
+
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+
+            assert!(archive.info().is_zip64);
+            assert_eq!(archive.info().records, count as u64);
+
+            let files = archive.list_files().await?;
+
+            assert_eq!(files.len(), count);
+
+            let last = &files[count - 1];
+
+            assert_eq!(
+                last.read(&mut archive).await?,
+                (count - 1).to_string().as_bytes()
+            );
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_modify() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("modify.zip");
+
+            fs::copy(
+                "../../resources/zip/Zip Test Python Data Descriptor.zip",
+                &path,
+            )
+            .await?;
+
+            let file_2 = fs::read("../../resources/unzipped contents/File 2.txt").await?;
+            let ipsum_1 =
+                fs::read("../../resources/unzipped contents/Large Files/ipsum 1.txt").await?;
+            let ipsum_2 =
+                fs::read("../../resources/unzipped contents/Large Files/ipsum 2.txt").await?;
+
+            let mut writer = ArchiveWriter::append(&path).await?;
+
+            writer.remove("File 1.txt").await?;
+            // Longer, so it's moved to the end.
+            writer.rename("File 2.txt", "Renamed File 2.txt").await?;
+            // Shorter, so it's rewritten in place.
+            writer
+                .rename("Large Files/ipsum 1.txt", "ipsum 1.txt")
+                .await?;
+            writer
+                .add_bytes("manifest.json", b"{}", EntryOptions::default())
+                .await?;
+
+            assert!(matches!(
+                writer.remove("File 1.txt").await,
+                Err(Error::EntryNotFound(_))
+            ));
+            assert!(matches!(
+                writer.rename("ipsum 1.txt", "Renamed File 2.txt").await,
+                Err(Error::EntryExists(_))
+            ));
+            assert!(matches!(
+                writer
+                    .rename("ipsum 1.txt", "a".repeat(u16::MAX as usize + 1))
+                    .await,
+                Err(Error::HeaderFieldTooLong {
+                    field: "file name",
+                    ..
+                })
+            ));
+
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+
+            let names = files
+                .iter()
+                .map(|v| v.file_name.as_str())
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                names,
+                [
+                    "ipsum 1.txt",
+                    "Large Files/ipsum 2.txt",
+                    "Renamed File 2.txt",
+                    "manifest.json"
+                ]
+            );
+
+            assert_eq!(files[0].read(&mut archive).await?, ipsum_1);
+            assert_eq!(files[1].read(&mut archive).await?, ipsum_2);
+            assert_eq!(files[2].read(&mut archive).await?, file_2);
+            assert_eq!(files[3].read(&mut archive).await?, b"{}");
+
+            // The entries are packed together, so walking them finds every one.
+            let mut reader = SequentialReader::open(&path).await?;
+            let mut count = 0;
+
+            while let Some(entry) = reader.next_entry().await? {
+                entry.read().await?;
+                count += 1;
+            }
+
+            assert_eq!(count, 4);
+
+            // Adding straight away writes over the Central Directory, not wherever it was last read.
+            fs::copy("../../resources/zip/Zip Test 7-Zip.zip", &path).await?;
+
+            let mut writer = ArchiveWriter::append(&path).await?;
+
+            writer
+                .add_bytes("manifest.json", b"{}", EntryOptions::default())
+                .await?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+
+            assert_eq!(
+                files.last().map(|v| v.file_name.as_str()),
+                Some("manifest.json")
+            );
+
+            for file in &files {
+                assert_eq!(
+                    file.read(&mut archive).await?.len() as u64,
+                    file.uncompressed_size
+                );
+            }
+
+            // The local name is longer than the one in the Central Directory.
+            fs::copy("../../resources/zip/Zip Test Python Local Name.zip", &path).await?;
+
+            let mut writer = ArchiveWriter::append(&path).await?;
+
+            writer.rename("ipsum 1.txt", "ipsum 9.txt").await?;
+            writer.rename("File 2.txt", "File 3.txt").await?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+
+            assert_eq!(files[0].file_name, "ipsum 9.txt");
+            assert_eq!(files[0].read(&mut archive).await?, ipsum_1);
+            assert_eq!(files[1].file_name, "File 3.txt");
+            assert_eq!(files[1].read(&mut archive).await?, file_2);
+
+            // Both headers now have the new names.
+            assert!(archive.test().await?.is_ok());
+
+            // The Unicode Path extra field is for the old name, so it's removed from both headers.
+            fs::copy("../../resources/zip/Zip Test Encodings.zip", &path).await?;
+
+            let mut writer = ArchiveWriter::append(&path).await?;
+
+            writer.rename("日本語 2.txt", "Renamed 2.txt").await?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+
+            assert_eq!(files[3].file_name, "Renamed 2.txt");
+            assert!(files[3].extra_field_raw.is_empty());
+
+            let extent = files[3].extent(&mut archive).await?;
+
+            assert!(extent.local_header.extra_field_raw.is_empty());
+            assert_eq!(extent.local_header.file_name, "Renamed 2.txt");
+            assert_eq!(files[3].read(&mut archive).await?, file_2);
+            assert!(archive.test().await?.is_ok());
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_copy_entry() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("copy-entry.zip");
+
+            let file_2 = fs::read("../../resources/unzipped contents/File 2.txt").await?;
+            let ipsum_2 =
+                fs::read("../../resources/unzipped contents/Large Files/ipsum 2.txt").await?;
+
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer
+                .add_bytes("manifest.json", b"{}", EntryOptions::default())
+                .await?;
+
+            // With a data descriptor.
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Python Data Descriptor.zip").await?;
+
+            for file in archive.list_files().await? {
+                if file.file_name.starts_with("Large Files/") {
+                    writer.copy_entry(&mut archive, &file).await?;
+                }
+            }
+
+            // Encrypted, which doesn't need the password.
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Info-ZIP ZipCrypto.zip").await?;
+            let files = archive.list_files().await?;
+            let file = files
+                .iter()
+                .find(|v| v.file_name == "File 2.txt")
+                .expect("File 2.txt");
+
+            writer.copy_entry(&mut archive, file).await?;
+
+            assert!(matches!(
+                writer.copy_entry(&mut archive, file).await,
+                Err(Error::EntryExists(_))
+            ));
+
+            // Stored across the volumes of a split archive.
+            let mut archive =
+                Archive::open_split("../../resources/zip/Zip Test Info-ZIP Split.zip").await?;
+            let files = archive.list_files().await?;
+
+            writer.copy_entry(&mut archive, &files[0]).await?;
+
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+
+            let names = files
+                .iter()
+                .map(|v| v.file_name.as_str())
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                names,
+                [
+                    "manifest.json",
+                    "Large Files/ipsum 1.txt",
+                    "Large Files/ipsum 2.txt",
+                    "File 2.txt",
+                    "APPNOTE-6.3.10.TXT"
+                ]
+            );
+
+            assert_eq!(files[2].read(&mut archive).await?, ipsum_2);
+            assert!(files[3].is_encrypted());
+            assert_eq!(
+                files[3]
+                    .read_with_password(&mut archive, "archiver")
+                    .await?,
+                file_2
+            );
+            assert_eq!(
+                files[4].read(&mut archive).await?,
+                fs::read("../../resources/zip/APPNOTE-6.3.10.TXT").await?
+            );
+
+            // Extra fields are kept as they're stored, even ones which wouldn't be written the same way again.
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer
+                .add_bytes("manifest.json", b"{}", EntryOptions::default())
+                .await?;
+            writer.finish().await?;
+
+            // Swap the extended timestamp in the Central Directory for a Unix owner of the same size with 1 byte IDs.
+            let unix_owner = [0x75, 0x78, 0x05, 0x00, 0x01, 0x01, 0xE8, 0x01, 0xE8];
+
+            let mut bytes = fs::read(&path).await?;
+            let central_dir = bytes
+                .windows(4)
+                .position(|v| v == CENTRAL_DIR_SIG)
+                .expect("Central Directory");
+            let extra_start = central_dir + CENTRAL_DIR_SIZE_KNOWN + "manifest.json".len();
+
+            bytes[extra_start..extra_start + unix_owner.len()].copy_from_slice(&unix_owner);
+            fs::write(&path, &bytes).await?;
+
+            let copy = temp_path("copy-entry-extra.zip");
+
+            let mut archive = Archive::open(&path).await?;
+            let files = archive.list_files().await?;
+
+            let mut writer = ArchiveWriter::create(&copy).await?;
+            writer.copy_entry(&mut archive, &files[0]).await?;
+            writer.finish().await?;
+
+            ArchiveWriter::append(&path).await?.finish().await?;
+
+            for path in [&path, &copy] {
+                let mut archive = Archive::open(path).await?;
+
+                assert_eq!(archive.list_files().await?[0].extra_field_raw, unix_owner);
+            }
+
+            fs::remove_file(&copy).await?;
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_limits() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test Python Data Descriptor.zip";
+            let zeros = "../../resources/zip/Zip Test Python Zeros Streamed.zip";
+
+            let with_limits = |limits| ArchiveOptions {
+                limits,
+                ..ArchiveOptions::default()
+            };
+
+            // Checked before the Central Directory is read.
+            let options = with_limits(ArchiveLimits {
+                max_entries: Some(3),
+                ..ArchiveLimits::default()
+            });
+
+            assert!(matches!(
+                Archive::open_with_options(path, options).await,
+                Err(Error::TooManyEntries { limit: 3, found: 4 })
+            ));
+
+            let options = with_limits(ArchiveLimits {
+                max_file_name_length: Some(16),
+                ..ArchiveLimits::default()
+            });
+
+            let mut archive = Archive::open_with_options(path, options).await?;
+
+            assert!(matches!(
+                archive.list_files().await,
+                Err(Error::FileNameTooLong { limit: 16, found: 23 })
+            ));
+
+            // The Central Directory sizes.
+            let options = with_limits(ArchiveLimits {
+                max_compression_ratio: Some(100),
+                ..ArchiveLimits::default()
+            });
+
+            let mut archive = Archive::open_with_options(zeros, options).await?;
+
+            assert!(matches!(
+                archive.list_files().await,
+                Err(Error::CompressionRatioExceeded {
+                    limit: 100,
+                    compressed_size: 4080,
+                    uncompressed_size: 4194304
+                })
+            ));
+
+            let options = with_limits(ArchiveLimits {
+                max_total_uncompressed_size: Some(1024 * 1024),
+                ..ArchiveLimits::default()
+            });
+
+            let mut archive = Archive::open_with_options(zeros, options).await?;
+
+            assert!(matches!(
+                archive.list_files().await,
+                Err(Error::UncompressedSizeExceeded { .. })
+            ));
+
+            // The sizes aren't known until the data has been decompressed, so it's stopped part way through.
+            let options = with_limits(ArchiveLimits {
+                max_compression_ratio: Some(100),
+                ..ArchiveLimits::default()
+            });
+
+            let mut reader = SequentialReader::open_with_options(zeros, options).await?;
+            let entry = reader.next_entry().await?.expect("zeros.bin");
+
+            assert!(matches!(
+                entry.read().await,
+                Err(Error::CompressionRatioExceeded { uncompressed_size, .. }) if uncompressed_size < 4194304
+            ));
+
+            // Counted across entries.
+            let options = with_limits(ArchiveLimits {
+                max_total_uncompressed_size: Some(10_000),
+                ..ArchiveLimits::default()
+            });
+
+            let mut reader = SequentialReader::open_with_options(path, options).await?;
+            let mut result = Ok(());
+
+            while let Some(entry) = reader.next_entry().await? {
+                if let Err(e) = entry.read().await {
+                    result = Err(e);
+                    break;
+                }
+            }
+
+            assert!(matches!(
+                result,
+                Err(Error::UncompressedSizeExceeded { limit: 10_000 })
+            ));
+
+            assert!(matches!(
+                Archive::recover_with_options(zeros, options).await,
+                Err(Error::UncompressedSizeExceeded { limit: 10_000 })
+            ));
+
+            // PPMd allocates however much memory the entry data asks for.
+            let ppmd = "../../resources/zip/Zip Test PPMd.zip";
+            let options = with_limits(ArchiveLimits {
+                max_decoder_memory: Some(1024 * 1024),
+                ..ArchiveLimits::default()
+            });
+
+            let mut archive = Archive::open_with_options(ppmd, options).await?;
+            let files = archive.list_files().await?;
+
+            let error = files[0]
+                .read(&mut archive)
+                .await
+                .expect_err("16 MB model");
+
+            assert!(error.is_limit());
+            assert!(matches!(
+                error,
+                Error::DecoderMemoryExceeded {
+                    limit: 1048576,
+                    found: Some(16777216)
+                }
+            ));
+            assert_eq!(files[2].file_name, "Large Files/ipsum 2.txt");
+            assert!(files[2].read(&mut archive).await.is_ok());
+
+            let mut reader = SequentialReader::open_with_options(ppmd, options).await?;
+            let entry = reader.next_entry().await?.expect("File 2.txt");
+
+            assert!(matches!(
+                entry.read().await,
+                Err(Error::DecoderMemoryExceeded { .. })
+            ));
+
+            // Point the second record at the first entry, like a non-recursive zip bomb.
+            let mut bytes = fs::read(path).await?;
+
+            let records = bytes
+                .windows(SIGNATURE_SIZE)
+                .enumerate()
+                .filter(|(_, v)| *v == CENTRAL_DIR_SIG)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+
+            bytes[records[1] + 42..records[1] + 46].copy_from_slice(&0u32.to_le_bytes());
+
+            let overlapping = temp_path("overlapping.zip");
+            fs::write(&overlapping, &bytes).await?;
+
+            let mut archive = Archive::open(&overlapping).await?;
+
+            assert!(matches!(
+                archive.list_files().await,
+                Err(Error::OverlappingEntries { .. })
+            ));
+
+            fs::remove_file(&overlapping).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_test() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test Python Data Descriptor.zip";
+
+            let report = Archive::open(path).await?.test().await?;
+
+            assert!(report.is_ok());
+            assert_eq!(report.entries.len(), 4);
+
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Info-ZIP ZipCrypto.zip").await?;
+
+            let report = archive.test().await?;
+
+            assert!(!report.is_ok());
+            assert!(report
+                .entries
+                .iter()
+                .all(|v| matches!(v.error, Some(Error::PasswordRequired))));
+
+            assert!(archive.test_with_password("archiver").await?.is_ok());
+
+            // Damage a copy of the archive in every way that's checked.
+            let mut bytes = fs::read(path).await?;
+
+            let find = |bytes: &[u8], value: &[u8]| {
+                bytes
+                    .windows(value.len())
+                    .position(|v| v == value)
+                    .expect("value")
+            };
+
+            // The Local File Header of "File 1.txt".
+            let file_1 = find(&bytes, b"File 1.txt");
+            bytes[file_1 + 5] = b'X';
+
+            // The data of "Large Files/ipsum 1.txt".
+            let ipsum_1 = find(&bytes, b"Large Files/ipsum 1.txt");
+            bytes[ipsum_1 + 100] ^= 0xFF;
+
+            // The Central Directory record of "File 2.txt" is renamed to "File 1.txt".
+            let central_dir = find(&bytes, &CENTRAL_DIR_SIG);
+            let file_2 = central_dir + find(&bytes[central_dir..], b"File 2.txt");
+            bytes[file_2 + 5] = b'1';
+
+            // Bytes between the last entry and the Central Directory.
+            bytes.splice(central_dir..central_dir, [0; 16]);
+
+            let end_header = bytes.len() - 22;
+            assert_eq!(bytes[end_header..end_header + 4], END_CENTRAL_DIR_SIG);
+            bytes[end_header + 16..end_header + 20]
+                .copy_from_slice(&(central_dir as u32 + 16).to_le_bytes());
+
+            let damaged = temp_path("test-damaged.zip");
+            fs::write(&damaged, &bytes).await?;
+
+            let report = Archive::open(&damaged).await?.test().await?;
+
+            assert!(!report.is_ok());
+
+            assert_eq!(
+                report.findings,
+                [
+                    ArchiveFinding::DuplicateFileName("File 1.txt".to_string()),
+                    ArchiveFinding::UnaccountedBytes {
+                        offset: central_dir as u64,
+                        size: 16
+                    }
+                ]
+            );
+
+            let entries = &report.entries;
+
+            assert_eq!(
+                entries[0].mismatches,
+                [HeaderMismatch::FileName {
+                    central: "File 1.txt".to_string(),
+                    local: "File X.txt".to_string()
+                }]
+            );
+            assert!(entries[0].error.is_none());
+
+            assert_eq!(
+                entries[1].mismatches,
+                [HeaderMismatch::FileName {
+                    central: "File 1.txt".to_string(),
+                    local: "File 2.txt".to_string()
+                }]
+            );
+
+            assert!(entries[2].mismatches.is_empty());
+            assert!(entries[2].error.is_some());
+
+            assert!(entries[3].is_ok());
+
+            fs::remove_file(&damaged).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_extract() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("extract.zip");
+            let dir = temp_path("extract");
+
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer
+                .add_bytes("File.txt", b"File contents", EntryOptions::default())
+                .await?;
+            writer
+                .add_directory("Folder", EntryOptions::default())
+                .await?;
+            writer
+                .add_bytes("Folder/Nested.txt", b"Nested", EntryOptions::stored())
+                .await?;
+            // Without a folder entry.
+            writer
+                .add_bytes(
+                    "./Deeper/Still\\Nested.txt",
+                    b"Deeper",
+                    EntryOptions::stored(),
+                )
+                .await?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+
+            let written = archive.extract_to(&dir, ExtractOptions::default()).await?;
+
+            assert_eq!(
+                written,
+                [
+                    dir.join("File.txt"),
+                    dir.join("Folder"),
+                    dir.join("Folder/Nested.txt"),
+                    dir.join("Deeper/Still/Nested.txt"),
+                ]
+            );
+            assert_eq!(fs::read(dir.join("File.txt")).await?, b"File contents");
+            assert_eq!(fs::read(dir.join("Folder/Nested.txt")).await?, b"Nested");
+            assert_eq!(
+                fs::read(dir.join("Deeper/Still/Nested.txt")).await?,
+                b"Deeper"
+            );
+
+            fs::write(dir.join("File.txt"), b"Changed").await?;
+
+            let extract = |overwrite| ExtractOptions {
+                overwrite,
+                ..ExtractOptions::default()
+            };
+
+            // Error
+            assert!(matches!(
+                archive.extract_to(&dir, extract(OverwritePolicy::Error)).await,
+                Err(Error::AlreadyExists(v)) if v == dir.join("File.txt")
+            ));
+
+            // Skip. Only the folder is written.
+            let written = archive
+                .extract_to(&dir, extract(OverwritePolicy::Skip))
+                .await?;
+
+            assert_eq!(written, [dir.join("Folder")]);
+            assert_eq!(fs::read(dir.join("File.txt")).await?, b"Changed");
+
+            // Rename
+            let written = archive
+                .extract_to(&dir, extract(OverwritePolicy::Rename))
+                .await?;
+
+            assert_eq!(written[0], dir.join("File (1).txt"));
+            assert_eq!(written[3], dir.join("Deeper/Still/Nested (1).txt"));
+            assert_eq!(fs::read(dir.join("File (1).txt")).await?, b"File contents");
+
+            let written = archive
+                .extract_to(&dir, extract(OverwritePolicy::Rename))
+                .await?;
+
+            assert_eq!(written[0], dir.join("File (2).txt"));
+
+            // Overwrite
+            archive
+                .extract_to(&dir, extract(OverwritePolicy::Overwrite))
+                .await?;
+
+            assert_eq!(fs::read(dir.join("File.txt")).await?, b"File contents");
+
+            fs::remove_dir_all(&dir).await?;
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_extract_unsafe_paths() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = temp_path("extract-unsafe.zip");
+            let dir = temp_path("extract-unsafe");
+
+            for name in [
+                "../Outside.txt",
+                "Folder/../../Outside.txt",
+                "..\\Outside.txt",
+                "Folder\\..\\..\\Outside.txt",
+                "/tmp/Outside.txt",
+                "\\\\server\\share\\Outside.txt",
+                "C:/Outside.txt",
+                "C:Outside.txt",
+            ] {
+                let mut writer = ArchiveWriter::create(&path).await?;
+
+                writer
+                    .add_bytes("Safe.txt", b"Safe", EntryOptions::stored())
+                    .await?;
+                writer
+                    .add_bytes(name, b"Unsafe", EntryOptions::stored())
+                    .await?;
+                writer.finish().await?;
+
+                let mut archive = Archive::open(&path).await?;
+
+                assert!(
+                    matches!(
+                        archive.extract_to(&dir, ExtractOptions::default()).await,
+                        Err(Error::UnsafePath(v)) if v == name
+                    ),
+                    "{name}"
+                );
+
+                // Nothing is written when any name is unsafe.
+                assert!(!fs::try_exists(&dir).await?);
+            }
+
+            // "." components are ignored, and ":" is only unsafe on Windows.
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer.add_directory("./", EntryOptions::default()).await?;
+            writer
+                .add_bytes("Folder/./Item.txt", b"Item", EntryOptions::stored())
+                .await?;
+            writer
+                .add_bytes("Notes: Draft.txt", b"Notes", EntryOptions::stored())
+                .await?;
+            writer.finish().await?;
+
+            let result = Archive::open(&path)
+                .await?
+                .extract_to(&dir, ExtractOptions::default())
+                .await;
+
+            if cfg!(windows) {
+                assert!(matches!(result, Err(Error::UnsafePath(v)) if v == "Notes: Draft.txt"));
+            } else {
+                assert_eq!(
+                    result?,
+                    [dir.join("Folder/Item.txt"), dir.join("Notes: Draft.txt")]
+                );
+
+                fs::remove_dir_all(&dir).await?;
+            }
+
+            fs::remove_file(&path).await?;
+
+            // Links which are safe on their own, but lead outside of the destination through another link.
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                let parent = temp_path("extract-unsafe-links");
+                let dir = parent.join("Destination");
+
+                fs::create_dir_all(&parent).await?;
+                fs::set_permissions(&parent, std::fs::Permissions::from_mode(0o755)).await?;
+
+                for (path, name) in [
+                    // "d/b" links to "..", so "a" to "d/b/.." would be the folder above.
+                    ("../../resources/zip/Zip Test Python Symlink Chain.zip", "a"),
+                    // "a" links to ".", then the folder "a/" would have its permissions set through it.
+                    (
+                        "../../resources/zip/Zip Test Python Symlink Folder.zip",
+                        "a/",
+                    ),
+                    // "e" to "d/l/.." is extracted before "d/l" links to "..".
+                    ("../../resources/zip/Zip Test Python Symlink Order.zip", "e"),
+                    // The target is longer than any path could be.
+                    (
+                        "../../resources/zip/Zip Test Python Symlink Long.zip",
+                        "long",
+                    ),
+                ] {
+                    let mut archive = Archive::open(path).await?;
+
+                    assert!(
+                        matches!(
+                            archive.extract_to(&dir, ExtractOptions::default()).await,
+                            Err(Error::UnsafePath(v)) if v == name
+                        ),
+                        "{path}"
+                    );
+
+                    let mode = fs::metadata(&parent).await?.permissions().mode();
+                    assert_eq!(mode & 0o777, 0o755, "{path}");
+
+                    fs::remove_dir_all(&dir).await?;
+                }
+
+                fs::remove_dir_all(&parent).await?;
+            }
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_entry_kind() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            // MS-DOS attributes
+            let mut archive = Archive::open("../../resources/zip/Zip Test 7-Zip.zip").await?;
+
+            let kinds = archive
+                .list_files()
+                .await?
+                .iter()
+                .map(|v| (v.kind(), v.unix_mode()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                kinds,
+                [
+                    (EntryKind::Directory, None),
+                    (EntryKind::File, None),
+                    (EntryKind::File, None),
+                    (EntryKind::Directory, None),
+                    (EntryKind::File, None),
+                ]
+            );
+
+            // Unix modes
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Info-ZIP Unix.zip").await?;
+
+            let kinds = archive
+                .list_files()
+                .await?
+                .iter()
+                .map(|v| (v.file_name.clone(), v.kind(), v.unix_permissions()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                kinds,
+                [
+                    ("File 2.txt".into(), EntryKind::File, Some(0o644)),
+                    ("Scripts/".into(), EntryKind::Directory, Some(0o755)),
+                    ("Scripts/run.sh".into(), EntryKind::File, Some(0o755)),
+                    ("Scripts/link.txt".into(), EntryKind::Symlink, Some(0o777)),
+                    ("Private/".into(), EntryKind::Directory, Some(0o700)),
+                    ("Private/key.txt".into(), EntryKind::File, Some(0o600)),
+                    ("Scripts Link".into(), EntryKind::Symlink, Some(0o777)),
+                ]
+            );
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_extract_unix() -> Result<(), Error> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let dir = temp_path("extract-unix");
+
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Info-ZIP Unix.zip").await?;
+
+            archive.extract_to(&dir, ExtractOptions::default()).await?;
+
+            let mode = |path: &str| {
+                let path = dir.join(path);
+
+                async move {
+                    Result::<_, Error>::Ok(fs::metadata(path).await?.permissions().mode() & 0o777)
+                }
+            };
+
+            assert_eq!(mode("Scripts/run.sh").await?, 0o755);
+            assert_eq!(mode("Private").await?, 0o700);
+            assert_eq!(mode("Private/key.txt").await?, 0o600);
+
+            assert_eq!(
+                fs::read_link(dir.join("Scripts/link.txt")).await?,
+                Path::new("../File 2.txt")
+            );
+            assert_eq!(
+                fs::read(dir.join("Scripts/link.txt")).await?,
+                fs::read("../../resources/unzipped contents/File 2.txt").await?
+            );
+            assert_eq!(
+                fs::read_link(dir.join("Scripts Link")).await?,
+                Path::new("Scripts")
+            );
+
+            // Links are replaced rather than written through.
+            let written = archive
+                .extract_to(
+                    &dir,
+                    ExtractOptions {
+                        overwrite: OverwritePolicy::Overwrite,
+                        ..ExtractOptions::default()
+                    },
+                )
+                .await?;
+
+            assert_eq!(written.len(), 7);
+
+            fs::remove_dir_all(&dir).await?;
+
+            // Links which point outside of the destination.
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Python Symlink Escape.zip").await?;
+
+            assert!(matches!(
+                archive.extract_to(&dir, ExtractOptions::default()).await,
+                Err(Error::UnsafePath(v)) if v == "Folder/Escape"
+            ));
+
+            fs::remove_dir_all(&dir).await?;
+
+            // Writing through a link.
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Python Symlink Write Through.zip")
+                    .await?;
+
+            assert!(matches!(
+                archive.extract_to(&dir, ExtractOptions::default()).await,
+                Err(Error::UnsafePath(v)) if v == "Link/File.txt"
+            ));
+
+            fs::remove_dir_all(&dir).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_timestamps() -> Result<(), Error> {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            // NTFS
+            let mut archive = Archive::open("../../resources/zip/Zip Test 7-Zip.zip").await?;
+            let file = archive.list_files().await?.remove(2);
+
+            assert_eq!(file.file_name, "File 2.txt");
+            assert_eq!(
+                file.dos_last_modified(),
+                DosDateTime {
+                    year: 2022,
+                    month: 11,
+                    day: 5,
+                    hour: 21,
+                    minute: 40,
+                    second: 42,
+                }
+            );
+            assert_eq!(
+                file.last_modified(),
+                EntryTime::Utc(UNIX_EPOCH + Duration::new(1_667_709_640, 396_298_700))
+            );
+            assert_eq!(
+                file.last_accessed(),
+                Some(UNIX_EPOCH + Duration::new(1_667_709_651, 350_368_500))
+            );
+
+            // The MS-DOS time was recorded in UTC-7, and rounded up.
+            assert_eq!(
+                file.dos_last_modified().to_system_time(-7 * 3600),
+                UNIX_EPOCH + Duration::from_secs(1_667_709_642)
+            );
+
+            // MS-DOS only
+            let mut archive = Archive::open("../../resources/zip/Zip Test Windows.zip").await?;
+            let file = archive.list_files().await?.remove(0);
+
+            assert_eq!(
+                file.last_modified(),
+                EntryTime::Local(DosDateTime::from_fields(
+                    file.file_last_mod_time,
+                    file.file_last_mod_date
+                ))
+            );
+            assert_eq!(file.last_accessed(), None);
+
+            // Extended timestamp
+            let mut archive = Archive::open("../../resources/zip/Zip Test Info-ZIP.zip").await?;
+            let file = archive.list_files().await?.remove(0);
+            let modified = UNIX_EPOCH + Duration::from_secs(1_668_250_620);
+
+            assert_eq!(file.last_modified(), EntryTime::Utc(modified));
+            assert_eq!(file.last_modified().to_system_time(3600), modified);
+
+            let dos = DosDateTime::from_system_time(modified, 0);
+
+            assert_eq!(
+                dos,
+                DosDateTime {
+                    year: 2022,
+                    month: 11,
+                    day: 12,
+                    hour: 10,
+                    minute: 57,
+                    second: 0,
+                }
+            );
+            assert_eq!(file.dos_last_modified(), dos);
+            assert_eq!(dos.to_system_time(0), modified);
+
+            let (time, date) = dos.to_fields();
+
+            assert_eq!(DosDateTime::from_fields(time, date), dos);
+            assert_eq!(DosDateTime::from_system_time(modified, 3600).hour, 11);
+
+            // Extracting
+            let dir = temp_path("extract-times");
+
+            archive.extract_to(&dir, ExtractOptions::default()).await?;
+
+            let mut reader = ArchiveReader::init(&mut archive.file).await?;
+            let local = LocalFileHeader::parse(&mut reader, file.relative_offset, None).await?;
+            let metadata = fs::metadata(dir.join("File 2.txt")).await?;
+
+            assert_eq!(metadata.modified()?, modified);
+            assert_eq!(Some(metadata.accessed()?), local.last_accessed());
+
+            fs::remove_dir_all(&dir).await?;
+
+            archive
+                .extract_to(
+                    &dir,
+                    ExtractOptions {
+                        set_times: false,
+                        ..ExtractOptions::default()
+                    },
+                )
+                .await?;
+
+            assert!(fs::metadata(dir.join("File 2.txt")).await?.modified()? > modified);
+
+            fs::remove_dir_all(&dir).await?;
+
+            // Writing. The MS-DOS time is local, so the extended timestamp is what makes it UTC.
+            let path = temp_path("writer-times.zip");
+            let written = UNIX_EPOCH + Duration::from_secs(1_700_000_001);
+
+            let mut writer = ArchiveWriter::create(&path).await?;
+
+            writer
+                .add_bytes(
+                    "time.txt",
+                    b"Time",
+                    EntryOptions {
+                        last_modified: Some(written),
+                        utc_offset: 3600,
+                        ..EntryOptions::stored()
+                    },
+                )
+                .await?;
+            writer.finish().await?;
+
+            let mut archive = Archive::open(&path).await?;
+            let file = archive.list_files().await?.remove(0);
+
+            assert_eq!(file.last_modified(), EntryTime::Utc(written));
+            assert_eq!(
+                file.dos_last_modified(),
+                DosDateTime::from_system_time(written, 3600)
+            );
+            assert_eq!(
+                file.dos_last_modified().to_system_time(3600),
+                written - Duration::from_secs(1)
+            );
+
+            let mut reader = SequentialReader::open(&path).await?;
+            let entry = reader.next_entry().await?.expect("time.txt");
+
+            assert_eq!(entry.header().last_modified(), EntryTime::Utc(written));
+
+            fs::remove_file(&path).await?;
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_extra_fields() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let mut archive = Archive::open("../../resources/zip/Zip Test 7-Zip.zip").await?;
+
+            for file in archive.list_files().await? {
+                assert!(matches!(file.extra_field[..], [ExtraField::Ntfs(_)]));
+            }
+
+            let mut archive = Archive::open("../../resources/zip/Zip Test Info-ZIP.zip").await?;
+
+            for file in archive.list_files().await? {
+                assert_eq!(
+                    file.extra_field,
+                    [
+                        ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+                            flags: 0b11,
+                            last_modified: Some(1_668_250_620),
+                            last_accessed: None,
+                            created: None,
+                        }),
+                        ExtraField::UnixOwner(UnixOwner {
+                            version: 1,
+                            uid: 0,
+                            gid: 0
+                        })
+                    ]
+                );
+
+                // The Local File Header also has the access time.
+                let mut reader = ArchiveReader::init(&mut archive.file).await?;
+                let local = LocalFileHeader::parse(&mut reader, file.relative_offset, None).await?;
+
+                assert!(matches!(
+                    local.extra_field[0],
+                    ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+                        last_modified: Some(1_668_250_620),
+                        last_accessed: Some(_),
+                        ..
+                    })
+                ));
+            }
+
+            let mut archive =
+                Archive::open("../../resources/zip/Zip Test Python Zip64.zip").await?;
+
+            let files = archive.list_files().await?;
+
+            assert_eq!(
+                files[4].extra_field,
+                [ExtraField::Zip64(Zip64ExtendedInfo {
+                    uncompressed_size: Some(10090),
+                    compressed_size: Some(10090),
+                    relative_offset: Some(6925),
+                    disk_start_number: None,
+                })]
+            );
+
+            Result::<_, Error>::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_extra_fields_round_trip() {
+        let fields = [
+            ExtraField::Zip64(Zip64ExtendedInfo {
+                uncompressed_size: Some(u32::MAX as u64 + 1),
+                compressed_size: None,
+                relative_offset: Some(u32::MAX as u64 + 2),
+                disk_start_number: None,
+            }),
+            ExtraField::Ntfs(NtfsTimestamps {
+                last_modified: 133_126_054_200_000_000,
+                last_accessed: 133_126_054_210_000_000,
+                created: 133_126_054_220_000_000,
+            }),
+            ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+                flags: 0b101,
+                last_modified: Some(1_668_250_620),
+                last_accessed: None,
+                created: Some(1_668_250_000),
+            }),
+            ExtraField::UnixOwner(UnixOwner {
+                version: 1,
+                uid: 1000,
+                gid: 100,
+            }),
+            ExtraField::UnicodePath(UnicodeExtraField {
+                version: 1,
+                crc_32: 0x1234_5678,
+                value: String::from("Fïlé 3.txt"),
+            }),
+            ExtraField::UnicodeComment(UnicodeExtraField {
+                version: 1,
+                crc_32: 0x8765_4321,
+                value: String::from("Ĉommènt"),
+            }),
+            ExtraField::Aes(AesExtraField {
+                version: 2,
+                vendor_id: *b"AE",
+                strength: 3,
+                compression: CompressionType::Deflate,
+            }),
+            ExtraField::Unknown {
+                id: 0xCAFE,
+                data: vec![1, 2, 3],
+            },
+        ];
+
+        let bytes = ExtraField::to_bytes(&fields);
+
+        let parsed = ExtraField::parse_all(
+            &bytes,
+            Zip64Fields {
+                uncompressed_size: true,
+                relative_offset: true,
+                ..Zip64Fields::default()
+            },
+        );
+
+        assert_eq!(parsed, fields);
+        assert_eq!(ExtraField::to_bytes(&parsed), bytes);
+    }
+
+    #[test]
+    fn zip_name_encodings() -> Result<(), Error> {
+        let rt = Runtime::new()?;
+
+        rt.block_on(async {
+            let path = "../../resources/zip/Zip Test Encodings.zip";
+
+            let mut archive = Archive::open(path).await?;
+
+            assert_eq!(archive.info().comment, "Archive ¢omment");
+
+            let files = archive.list_files().await?;
+
+            let names = files
+                .iter()
+                .map(|v| v.file_name.as_str())
+                .collect::<Vec<_>>();
+
+            // Shift_JIS isn't flagged, so it's decoded as CP437 by default.
+            assert_eq!(
+                names,
+                [
+                    "ô·û{îΩ.txt",
+                    "Fïlé 3.txt",
+                    "Ünïcödé.txt",
+                    "日本語 2.txt",
+                    "Renamed.txt"
+                ]
+            );
+
+            assert_eq!(files[0].file_name_raw, b"\x93\xfa\x96{\x8c\xea.txt");
+            assert_eq!(files[1].file_comment, "Café");
+            assert_eq!(files[2].file_comment, "Ĉommènt");
+
+            let options = ArchiveOptions {
+                fallback_encoding: Some(encoding_rs::SHIFT_JIS),
+                ..ArchiveOptions::default()
+            };
+
+            let mut archive = Archive::open_with_options(path, options).await?;
+            let files = archive.list_files().await?;
+
+            assert_eq!(files[0].file_name, "日本語.txt");
+            // Flagged as UTF-8 and the Unicode Path aren't affected.
+            assert_eq!(files[2].file_name, "Ünïcödé.txt");
+            assert_eq!(files[3].file_name, "日本語 2.txt");
+
+            let mut reader = SequentialReader::open_with_options(path, options).await?;
+            let entry = reader.next_entry().await?.expect("entry");
+
+            assert_eq!(entry.header().file_name, "日本語.txt");
+
+            // UTF-8 names which Info-ZIP didn't flag.
+            let path = "../../resources/zip/Zip Test Info-ZIP.zip";
+
+            let mut archive = Archive::open(path).await?;
+
+            assert_eq!(archive.list_files().await?[1].file_name, "F├»l├⌐ 3.txt");
+
+            let options = ArchiveOptions {
+                fallback_encoding: Some(encoding_rs::UTF_8),
+                ..ArchiveOptions::default()
+            };
+
+            let mut archive = Archive::open_with_options(path, options).await?;
 
-            //
+            assert_eq!(archive.list_files().await?[1].file_name, "Fïlé 3.txt");
 
             Result::<_, Error>::Ok(())
         })?;
@@ -0,0 +1,127 @@
+//! Limits which stop a malicious archive from using up memory or disk space.
+//!
+//! A zip bomb is a small archive which decompresses to something huge. Either its entries compress extremely
+//! well, or many entries share the same compressed data by having their Local File Headers overlap. The sizes
+//! in the headers are checked before anything is decompressed, and entries whose sizes are in a data descriptor
+//! are checked as they're decompressed instead. Some decoders also read how much memory to allocate from the
+//! entry data.
+
+use crate::{CentralDirHeader, Error, Result, LOCAL_FILE_HEADER_SIZE_KNOWN};
+
+/// Limits on what an archive can hold. Nothing is limited by default.
+///
+/// Overlapping entries are always rejected, since no archive is written that way.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ArchiveLimits {
+    /// Most bytes every entry can decompress to in total.
+    pub max_total_uncompressed_size: Option<u64>,
+    /// Most times larger an entry can be than its compressed data.
+    pub max_compression_ratio: Option<u64>,
+    /// Most entries the archive can hold.
+    pub max_entries: Option<u64>,
+    /// Longest file name, in bytes as it's stored.
+    pub max_file_name_length: Option<usize>,
+    /// Most memory a decoder can allocate for its model or dictionary, in bytes. Checked for PPMd, LZMA, XZ and
+    /// Zstandard, which read how much they need from the entry data. PPMd entries can ask for up to 256 MiB and the
+    /// others for gigabytes.
+    pub max_decoder_memory: Option<u64>,
+}
+
+impl ArchiveLimits {
+    pub(crate) fn check_entry_count(&self, count: u64) -> Result<()> {
+        match self.max_entries {
+            Some(limit) if count > limit => Err(Error::TooManyEntries {
+                limit,
+                found: count,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_file_name(&self, file_name: &[u8]) -> Result<()> {
+        match self.max_file_name_length {
+            Some(limit) if file_name.len() > limit => Err(Error::FileNameTooLong {
+                limit,
+                found: file_name.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the sizes of an entry. An empty entry can't be compressed, so anything it decompresses to is too much.
+    pub(crate) fn check_ratio(&self, compressed_size: u64, uncompressed_size: u64) -> Result<()> {
+        match self.max_compression_ratio {
+            Some(limit) if uncompressed_size > compressed_size.saturating_mul(limit) => {
+                Err(Error::CompressionRatioExceeded {
+                    limit,
+                    compressed_size,
+                    uncompressed_size,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_total(&self, total_uncompressed_size: u64) -> Result<()> {
+        match self.max_total_uncompressed_size {
+            Some(limit) if total_uncompressed_size > limit => {
+                Err(Error::UncompressedSizeExceeded { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_decoder_memory(&self, memory_size: u64) -> Result<()> {
+        match self.max_decoder_memory {
+            Some(limit) if memory_size > limit => Err(Error::DecoderMemoryExceeded {
+                limit,
+                found: Some(memory_size),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Error {
+    /// Did the archive go over one of the [`ArchiveLimits`].
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self,
+            Self::TooManyEntries { .. }
+                | Self::FileNameTooLong { .. }
+                | Self::CompressionRatioExceeded { .. }
+                | Self::UncompressedSizeExceeded { .. }
+                | Self::DecoderMemoryExceeded { .. }
+        )
+    }
+}
+
+/// Check that no entry starts inside the Local File Header or data of another.
+///
+/// Only the fixed part of the Local File Header is counted, since that's the least it can be.
+pub(crate) fn check_overlap(files: &[CentralDirHeader]) -> Result<()> {
+    let mut positions = files
+        .iter()
+        .map(|v| (v.current_disk_number, v.relative_offset, v))
+        .collect::<Vec<_>>();
+
+    positions.sort_by_key(|(disk, offset, _)| (*disk, *offset));
+
+    for pair in positions.windows(2) {
+        let (disk, offset, first) = pair[0];
+        let (next_disk, next_offset, second) = pair[1];
+
+        let end = offset
+            .saturating_add(LOCAL_FILE_HEADER_SIZE_KNOWN as u64)
+            .saturating_add(first.compressed_size);
+
+        if disk == next_disk && end > next_offset {
+            return Err(Error::OverlappingEntries {
+                first: first.file_name.clone(),
+                second: second.file_name.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,329 @@
+//! Recovers the entries of an archive without using the Central Directory.
+//!
+//! Interrupted downloads and writes lose the end of the archive, and with it the Central Directory and the
+//! End of Central Directory record. The Local File Headers are still there, so they're walked from the start
+//! instead. Each entry is read through to find where it ends, using its sizes, its data descriptor or where the
+//! compressed data marks its own end, and checked against its CRC-32. Anything which doesn't check out is
+//! reported as damaged and the search carries on from just after its header. Going over one of the
+//! [`ArchiveLimits`](crate::ArchiveLimits) stops the recovery instead.
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+};
+
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::{
+    Archive, ArchiveFile, ArchiveLimits, ArchiveOptions, ArchiveReader, CentralDirHeader,
+    DataDescriptor, EndCentralDirHeader, Error, FileReaderCache, LocalFileHeader, Result,
+    SequentialEntry, DATA_DESCRIPTOR_MAX_SIZE, DATA_DESCRIPTOR_SIG, LOCAL_FILE_HEADER_SIG,
+    SIGNATURE_SIZE,
+};
+
+/// Amount of the archive to read at a time while searching for a signature.
+const SEARCH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// What was found while recovering an archive.
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// How many entries were recovered. They're what [`Archive::list_files`] returns.
+    pub recovered: usize,
+    /// Entries which were found but couldn't be read, in the order they're stored.
+    pub damaged: Vec<DamagedEntry>,
+}
+
+impl RecoveryReport {
+    pub fn is_intact(&self) -> bool {
+        self.damaged.is_empty()
+    }
+}
+
+/// A Local File Header which was found, but whose entry couldn't be recovered.
+#[derive(Debug)]
+pub struct DamagedEntry {
+    /// Where the Local File Header starts.
+    pub offset: u64,
+    /// The file name, if the header could be read.
+    pub file_name: Option<String>,
+    /// Why it couldn't be recovered.
+    pub error: Error,
+}
+
+impl Archive {
+    /// Open an archive by walking its Local File Headers rather than reading the Central Directory.
+    ///
+    /// Used when [`Archive::open`] fails because the end of the archive is missing or damaged. The intact entries
+    /// can be read as normal. Since the Central Directory isn't used they don't have comments, attributes or the
+    /// host system.
+    pub async fn recover(path: impl AsRef<Path>) -> Result<(Self, RecoveryReport)> {
+        Self::recover_with_options(path, ArchiveOptions::default()).await
+    }
+
+    pub async fn recover_with_options(
+        path: impl AsRef<Path>,
+        options: ArchiveOptions,
+    ) -> Result<(Self, RecoveryReport)> {
+        let mut file = ArchiveFile::open(path).await?;
+
+        let mut files = Vec::new();
+        let mut report = RecoveryReport::default();
+
+        let mut offset = file.spanning_marker_size().await?;
+        let mut total_uncompressed_size = 0;
+
+        while let Some(header_offset) =
+            find_signature(&mut file, offset, LOCAL_FILE_HEADER_SIG).await?
+        {
+            match recover_entry(
+                &mut file,
+                header_offset,
+                options,
+                &mut total_uncompressed_size,
+            )
+            .await
+            {
+                Ok((header, end)) => {
+                    files.push(header);
+                    offset = end;
+
+                    options.limits.check_entry_count(files.len() as u64)?;
+                }
+
+                Err((_, error)) if error.is_limit() => return Err(error),
+
+                Err((file_name, error)) => {
+                    report.damaged.push(DamagedEntry {
+                        offset: header_offset,
+                        file_name,
+                        error,
+                    });
+
+                    offset = header_offset + SIGNATURE_SIZE as u64;
+                }
+            }
+        }
+
+        report.recovered = files.len();
+
+        let end_header = EndCentralDirHeader {
+            total_record_count: files.len() as u64,
+            ..EndCentralDirHeader::default()
+        };
+
+        let archive = Self {
+            file,
+            options,
+            file_cache: FileReaderCache::from_files(files),
+            end_header,
+        };
+
+        Ok((archive, report))
+    }
+}
+
+/// Read through the entry at `offset`. Returns a record for it and where it ends.
+///
+/// The error comes with the file name when the header could be read.
+async fn recover_entry(
+    file: &mut ArchiveFile,
+    offset: u64,
+    options: ArchiveOptions,
+    total_uncompressed_size: &mut u64,
+) -> std::result::Result<(CentralDirHeader, u64), (Option<String>, Error)> {
+    let header = async {
+        let mut reader = ArchiveReader::init(file).await?;
+
+        LocalFileHeader::parse(&mut reader, offset, options.fallback_encoding).await
+    }
+    .await
+    .map_err(|e| (None, e))?;
+
+    let file_name = header.file_name.clone();
+
+    read_entry(
+        file,
+        header,
+        offset,
+        options.limits,
+        total_uncompressed_size,
+    )
+    .await
+    .map_err(|e| (Some(file_name), e))
+}
+
+async fn read_entry(
+    file: &mut ArchiveFile,
+    mut header: LocalFileHeader,
+    offset: u64,
+    limits: ArchiveLimits,
+    total_uncompressed_size: &mut u64,
+) -> Result<(CentralDirHeader, u64)> {
+    limits.check_file_name(&header.file_name_raw)?;
+
+    let data_offset = offset + header.size();
+
+    let size_known = !header.has_data_descriptor() || header.compressed_size != 0;
+
+    // Without an end marker to decompress up to, the data descriptor has to be searched for.
+    if !size_known && (header.is_encrypted() || !header.compression.has_end_marker(header.gp_flag))
+    {
+        let descriptor = find_data_descriptor(file, data_offset, header.is_zip64).await?;
+
+        header.crc_32 = descriptor.crc_32;
+        header.compressed_size = descriptor.compressed_size;
+        header.uncompressed_size = descriptor.uncompressed_size;
+    }
+
+    if header.is_encrypted() {
+        let (header, end) = read_encrypted_entry(file, header, offset).await?;
+
+        // It isn't decompressed here, so it's the sizes which are checked.
+        limits.check_ratio(header.compressed_size, header.uncompressed_size)?;
+
+        *total_uncompressed_size = total_uncompressed_size.saturating_add(header.uncompressed_size);
+        limits.check_total(*total_uncompressed_size)?;
+
+        return Ok((header, end));
+    }
+
+    let mut next_offset = None;
+
+    let descriptor = SequentialEntry::new(
+        file,
+        &mut next_offset,
+        total_uncompressed_size,
+        header.clone(),
+        data_offset,
+        None,
+        limits,
+    )
+    .await?
+    .finish()
+    .await?;
+
+    if let Some(descriptor) = descriptor {
+        header.crc_32 = descriptor.crc_32;
+        header.compressed_size = descriptor.compressed_size;
+        header.uncompressed_size = descriptor.uncompressed_size;
+    }
+
+    let end = next_offset.ok_or(Error::MissingDataDescriptor)?;
+
+    Ok((CentralDirHeader::from_local_header(&header, offset), end))
+}
+
+/// Encrypted entries can't be checked without the password, so their sizes are trusted as long as the data
+/// is all there.
+async fn read_encrypted_entry(
+    file: &mut ArchiveFile,
+    mut header: LocalFileHeader,
+    offset: u64,
+) -> Result<(CentralDirHeader, u64)> {
+    let data_end = offset + header.size() + header.compressed_size;
+    let mut end = data_end;
+
+    if header.has_data_descriptor() {
+        let bytes = read_at(file, data_end, DATA_DESCRIPTOR_MAX_SIZE).await?;
+
+        let (descriptor, size) =
+            DataDescriptor::from_bytes(&bytes, header.is_zip64, header.compressed_size)
+                .ok_or(Error::MissingDataDescriptor)?;
+
+        if descriptor.compressed_size != header.compressed_size {
+            return Err(Error::SizeMismatch {
+                expected: descriptor.compressed_size,
+                found: header.compressed_size,
+            });
+        }
+
+        header.crc_32 = descriptor.crc_32;
+        header.uncompressed_size = descriptor.uncompressed_size;
+
+        end += size as u64;
+    } else {
+        let file_size = file.seek(SeekFrom::End(0)).await?;
+
+        if data_end > file_size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+    }
+
+    Ok((CentralDirHeader::from_local_header(&header, offset), end))
+}
+
+/// Find the data descriptor of an entry whose size isn't known.
+///
+/// Only descriptors with the optional signature can be found. The sizes it holds have to match where it is.
+pub(crate) async fn find_data_descriptor(
+    file: &mut ArchiveFile,
+    data_offset: u64,
+    is_zip64: bool,
+) -> Result<DataDescriptor> {
+    let mut start = data_offset;
+
+    while let Some(position) = find_signature(file, start, DATA_DESCRIPTOR_SIG).await? {
+        let compressed_size = position - data_offset;
+        let bytes = read_at(file, position, DATA_DESCRIPTOR_MAX_SIZE).await?;
+
+        if let Some((descriptor, _)) = DataDescriptor::from_bytes(&bytes, is_zip64, compressed_size)
+        {
+            if descriptor.compressed_size == compressed_size {
+                return Ok(descriptor);
+            }
+        }
+
+        start = position + 1;
+    }
+
+    Err(Error::MissingDataDescriptor)
+}
+
+/// Where the next `signature` at or after `start` is.
+async fn find_signature(
+    file: &mut ArchiveFile,
+    start: u64,
+    signature: [u8; SIGNATURE_SIZE],
+) -> Result<Option<u64>> {
+    file.seek(SeekFrom::Start(start)).await?;
+
+    let mut buffer = vec![0u8; SEARCH_CHUNK_SIZE];
+    // Where `buffer` starts in the file.
+    let mut position = start;
+    // Bytes carried over from the last read, since the signature could be split between reads.
+    let mut kept = 0;
+
+    loop {
+        let amount = file.read(&mut buffer[kept..]).await?;
+
+        if amount == 0 {
+            return Ok(None);
+        }
+
+        let filled = kept + amount;
+
+        if let Some(index) = buffer[..filled]
+            .windows(SIGNATURE_SIZE)
+            .position(|v| v == signature)
+        {
+            return Ok(Some(position + index as u64));
+        }
+
+        kept = filled.min(SIGNATURE_SIZE - 1);
+        buffer.copy_within(filled - kept..filled, 0);
+        position += (filled - kept) as u64;
+    }
+}
+
+/// Read up to `size` bytes at `offset`. Less is returned at the end of the file.
+pub(crate) async fn read_at(file: &mut ArchiveFile, offset: u64, size: usize) -> Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    let mut bytes = Vec::with_capacity(size);
+    (&mut *file)
+        .take(size as u64)
+        .read_to_end(&mut bytes)
+        .await?;
+
+    Ok(bytes)
+}
@@ -0,0 +1,402 @@
+//! Reads the entries in the order they're stored by walking the Local File Headers.
+//!
+//! Unlike [`Archive`](crate::Archive) the Central Directory isn't used, so the CRC-32 and sizes
+//! of entries written with a data descriptor are only known once their data has been read.
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, ReadBuf};
+
+use crate::{
+    recover::{find_data_descriptor, read_at},
+    ArchiveFile, ArchiveLimits, ArchiveOptions, ArchiveReader, CentralDirHeader, CompressionType,
+    DataDescriptor, EntryReader, Error, LocalFileHeader, Result, DATA_DESCRIPTOR_MAX_SIZE,
+};
+
+pub struct SequentialReader {
+    file: ArchiveFile,
+
+    options: ArchiveOptions,
+
+    /// Where the next Local File Header starts. Unknown until the current entry has been read through.
+    next_offset: Option<u64>,
+    /// The last entry returned and where its data starts.
+    current: Option<(LocalFileHeader, u64)>,
+
+    entry_count: u64,
+    /// What the entries read through so far decompressed to.
+    total_uncompressed_size: u64,
+}
+
+impl SequentialReader {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_options(path, ArchiveOptions::default()).await
+    }
+
+    pub async fn open_with_options(
+        path: impl AsRef<Path>,
+        options: ArchiveOptions,
+    ) -> Result<Self> {
+        Ok(Self::with_options(ArchiveFile::open(path).await?, options))
+    }
+
+    /// Read a split archive in order. See [`ArchiveFile::open_split`] for how the other volumes are found.
+    pub async fn open_split(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_split_with_options(path, ArchiveOptions::default()).await
+    }
+
+    pub async fn open_split_with_options(
+        path: impl AsRef<Path>,
+        options: ArchiveOptions,
+    ) -> Result<Self> {
+        Ok(Self::with_options(
+            ArchiveFile::open_split(path).await?,
+            options,
+        ))
+    }
+
+    pub fn new(file: impl Into<ArchiveFile>) -> Self {
+        Self::with_options(file, ArchiveOptions::default())
+    }
+
+    pub fn with_options(file: impl Into<ArchiveFile>, options: ArchiveOptions) -> Self {
+        Self {
+            file: file.into(),
+            options,
+            next_offset: Some(0),
+            current: None,
+            entry_count: 0,
+            total_uncompressed_size: 0,
+        }
+    }
+
+    /// Move onto the next entry, skipping the rest of the current one.
+    ///
+    /// Returns `None` once there are no more Local File Headers. Normally that's the start of the Central Directory.
+    /// Encrypted entries return [`Error::PasswordRequired`], and calling this again moves past them.
+    pub async fn next_entry(&mut self) -> Result<Option<SequentialEntry<'_>>> {
+        self.next_entry_inner(None).await
+    }
+
+    /// Move onto the next entry, decrypting it with `password` if it's encrypted.
+    pub async fn next_entry_with_password(
+        &mut self,
+        password: impl AsRef<[u8]>,
+    ) -> Result<Option<SequentialEntry<'_>>> {
+        self.next_entry_inner(Some(password.as_ref())).await
+    }
+
+    async fn next_entry_inner(
+        &mut self,
+        password: Option<&[u8]>,
+    ) -> Result<Option<SequentialEntry<'_>>> {
+        if self.next_offset.is_none() {
+            match self.current.take() {
+                // Encrypted entries are skipped without decrypting them, so the password isn't needed.
+                Some((header, data_offset)) if header.is_encrypted() => {
+                    self.next_offset =
+                        Some(encrypted_entry_end(&mut self.file, &header, data_offset).await?);
+                }
+
+                Some((header, data_offset)) => {
+                    SequentialEntry::new(
+                        &mut self.file,
+                        &mut self.next_offset,
+                        &mut self.total_uncompressed_size,
+                        header,
+                        data_offset,
+                        None,
+                        self.options.limits,
+                    )
+                    .await?
+                    .finish()
+                    .await?;
+                }
+
+                None => {}
+            }
+        }
+
+        let Some(mut offset) = self.next_offset else {
+            return Ok(None);
+        };
+
+        // Split archives start with a spanning signature before the first Local File Header.
+        if offset == 0 {
+            offset = self.file.spanning_marker_size().await?;
+        }
+
+        let mut reader = ArchiveReader::init(&mut self.file).await?;
+
+        let header =
+            match LocalFileHeader::parse(&mut reader, offset, self.options.fallback_encoding).await
+            {
+                Ok(v) => v,
+                Err(Error::MissingLocalHeader) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+
+        self.entry_count += 1;
+
+        self.options.limits.check_entry_count(self.entry_count)?;
+        self.options.limits.check_file_name(&header.file_name_raw)?;
+
+        let data_offset = offset + header.size();
+
+        // Without a data descriptor we already know where the next header is.
+        self.next_offset =
+            (!header.has_data_descriptor()).then_some(data_offset + header.compressed_size);
+        self.current = Some((header.clone(), data_offset));
+
+        Ok(Some(
+            SequentialEntry::new(
+                &mut self.file,
+                &mut self.next_offset,
+                &mut self.total_uncompressed_size,
+                header,
+                data_offset,
+                password,
+                self.options.limits,
+            )
+            .await?,
+        ))
+    }
+}
+
+/// An entry found by the [`SequentialReader`]. Reading it streams the decompressed contents.
+pub struct SequentialEntry<'a> {
+    header: LocalFileHeader,
+    data_offset: u64,
+    /// Including any encryption header. Unknown until the data has been read when it's in the data descriptor.
+    compressed_size: Option<u64>,
+    /// AE-2 doesn't store the CRC-32, so the data descriptor's is zero.
+    has_crc_32: bool,
+
+    reader: EntryReader<'a>,
+
+    next_offset: &'a mut Option<u64>,
+    /// Added to once the entry has been read through.
+    total_uncompressed_size: &'a mut u64,
+}
+
+impl<'a> SequentialEntry<'a> {
+    pub(crate) async fn new(
+        file: &'a mut ArchiveFile,
+        next_offset: &'a mut Option<u64>,
+        total_uncompressed_size: &'a mut u64,
+        header: LocalFileHeader,
+        data_offset: u64,
+        password: Option<&[u8]>,
+        limits: ArchiveLimits,
+    ) -> Result<SequentialEntry<'a>> {
+        if header.is_encrypted() && password.is_none() {
+            return Err(Error::PasswordRequired);
+        }
+
+        let has_data_descriptor = header.has_data_descriptor();
+
+        // Some writers still fill in the sizes when using a data descriptor.
+        let mut sizes = (!has_data_descriptor || header.compressed_size != 0)
+            .then_some((header.compressed_size, header.uncompressed_size));
+
+        // The encryption has to know where the data ends, and so does LZMA even with an end of stream marker.
+        // Instead the data descriptor is searched for.
+        if sizes.is_none() && (header.is_encrypted() || header.compression == CompressionType::Lzma)
+        {
+            let descriptor = find_data_descriptor(file, data_offset, header.is_zip64).await?;
+
+            sizes = Some((descriptor.compressed_size, descriptor.uncompressed_size));
+        }
+
+        let mut compression = header.compression;
+        let mut has_crc_32 = true;
+        let mut decryptor = None;
+        let mut data_size = sizes.map(|v| v.0);
+
+        file.seek(SeekFrom::Start(data_offset)).await?;
+
+        if let Some((compressed_size, uncompressed_size)) = sizes.filter(|_| header.is_encrypted())
+        {
+            let record = CentralDirHeader::from_local_header(
+                &LocalFileHeader {
+                    compressed_size,
+                    uncompressed_size,
+                    ..header.clone()
+                },
+                data_offset - header.size(),
+            );
+
+            let decryption = record.open_decryptor(file, password).await?;
+
+            compression = decryption.compression;
+            has_crc_32 = decryption.has_crc_32;
+            data_size = Some(decryption.size);
+            decryptor = Some(decryption.decryptor);
+        }
+
+        if sizes.is_none() && !compression.has_end_marker(header.gp_flag) {
+            return Err(Error::UnknownEntrySize(compression));
+        }
+
+        // The sizes can still be wrong, so the limits are also checked as the data is decompressed.
+        if let Some((compressed_size, uncompressed_size)) = sizes {
+            limits.check_ratio(compressed_size, uncompressed_size)?;
+            limits.check_total(total_uncompressed_size.saturating_add(uncompressed_size))?;
+        }
+
+        let source = file.take(data_size.unwrap_or(u64::MAX));
+
+        // The data descriptor is checked once we've read through the data.
+        let (crc_32, uncompressed_size) = if has_data_descriptor {
+            (None, None)
+        } else {
+            (
+                has_crc_32.then_some(header.crc_32),
+                Some(header.uncompressed_size),
+            )
+        };
+
+        let reader = EntryReader::new(
+            source,
+            compression.decoder(header.gp_flag, sizes.map(|v| v.1), limits)?,
+            crc_32,
+            uncompressed_size,
+        )
+        .with_limits(limits, *total_uncompressed_size);
+
+        Ok(Self {
+            header,
+            data_offset,
+            compressed_size: sizes.map(|v| v.0),
+            has_crc_32,
+            reader: match decryptor {
+                Some(value) => reader.with_decryptor(value),
+                None => reader,
+            },
+            next_offset,
+            total_uncompressed_size,
+        })
+    }
+
+    pub fn header(&self) -> &LocalFileHeader {
+        &self.header
+    }
+
+    /// Read the rest of the decompressed contents, then finish the entry.
+    pub async fn read(mut self) -> Result<Vec<u8>> {
+        let mut contents = Vec::new();
+
+        self.reader.read_to_end(&mut contents).await?;
+        self.finish().await?;
+
+        Ok(contents)
+    }
+
+    /// Read through the rest of the entry and its data descriptor, if it has one.
+    ///
+    /// The data descriptor values are checked against what was read.
+    pub async fn finish(mut self) -> Result<Option<DataDescriptor>> {
+        tokio::io::copy(&mut self.reader, &mut tokio::io::sink()).await?;
+
+        let total_in = self.reader.total_in();
+        let total_out = self.reader.total_out();
+        let crc_32 = self.reader.crc_32();
+
+        *self.total_uncompressed_size += total_out;
+
+        let file = self.reader.into_source().into_inner();
+
+        if !self.header.has_data_descriptor() {
+            *self.next_offset = Some(self.data_offset + self.header.compressed_size);
+
+            return Ok(None);
+        }
+
+        let compressed_size = self.compressed_size.unwrap_or(total_in);
+
+        let data_end = self.data_offset + compressed_size;
+
+        file.seek(SeekFrom::Start(data_end)).await?;
+
+        let mut buffer = [0u8; DATA_DESCRIPTOR_MAX_SIZE];
+        let mut filled = 0;
+
+        while filled < buffer.len() {
+            let amount = file.read(&mut buffer[filled..]).await?;
+
+            if amount == 0 {
+                break;
+            }
+
+            filled += amount;
+        }
+
+        let (descriptor, size) =
+            DataDescriptor::from_bytes(&buffer[..filled], self.header.is_zip64, compressed_size)
+                .ok_or(Error::MissingDataDescriptor)?;
+
+        if descriptor.compressed_size != compressed_size {
+            return Err(Error::SizeMismatch {
+                expected: descriptor.compressed_size,
+                found: compressed_size,
+            });
+        }
+
+        if descriptor.uncompressed_size != total_out {
+            return Err(Error::SizeMismatch {
+                expected: descriptor.uncompressed_size,
+                found: total_out,
+            });
+        }
+
+        if self.has_crc_32 && descriptor.crc_32 != crc_32 {
+            return Err(Error::ChecksumMismatch {
+                expected: descriptor.crc_32,
+                found: crc_32,
+            });
+        }
+
+        *self.next_offset = Some(data_end + size as u64);
+
+        Ok(Some(descriptor))
+    }
+}
+
+/// Where an encrypted entry with a data descriptor ends, found without decrypting it.
+async fn encrypted_entry_end(
+    file: &mut ArchiveFile,
+    header: &LocalFileHeader,
+    data_offset: u64,
+) -> Result<u64> {
+    let compressed_size = match header.compressed_size {
+        0 => {
+            find_data_descriptor(file, data_offset, header.is_zip64)
+                .await?
+                .compressed_size
+        }
+        v => v,
+    };
+
+    let data_end = data_offset + compressed_size;
+    let bytes = read_at(file, data_end, DATA_DESCRIPTOR_MAX_SIZE).await?;
+
+    let (_, size) = DataDescriptor::from_bytes(&bytes, header.is_zip64, compressed_size)
+        .ok_or(Error::MissingDataDescriptor)?;
+
+    Ok(data_end + size as u64)
+}
+
+impl<'a> AsyncRead for SequentialEntry<'a> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
+    }
+}
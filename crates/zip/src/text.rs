@@ -0,0 +1,58 @@
+//! Decodes file names and comments.
+//!
+//! 4.4.4 If general purpose bit 11 is set, the filename and comment MUST support The Unicode Standard, Version 4.1.0 or greater using the character encoding form defined by the UTF-8 storage specification.
+//! Otherwise they're expected to be in the original IBM PC character encoding (CP437), although plenty of archivers used the local codepage instead.
+
+use encoding_rs::Encoding;
+
+use crate::UnicodeExtraField;
+
+/// CP437 characters for the bytes 0x80 to 0xFF. The lower half is ASCII.
+#[rustfmt::skip]
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+pub(crate) fn decode_cp437(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&v| {
+            if v < 0x80 {
+                v as char
+            } else {
+                CP437_HIGH[v as usize - 0x80]
+            }
+        })
+        .collect()
+}
+
+/// Decode a file name or comment from a header.
+///
+/// When it isn't flagged as UTF-8 the Info-ZIP Unicode extra field is used if its CRC-32 matches the header bytes.
+/// Otherwise it's decoded with the fallback encoding, or CP437 if there isn't one.
+pub(crate) fn decode_text(
+    bytes: &[u8],
+    is_utf8: bool,
+    unicode: Option<&UnicodeExtraField>,
+    fallback: Option<&'static Encoding>,
+) -> String {
+    if is_utf8 {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+
+    if let Some(unicode) = unicode.filter(|v| v.crc_32 == crc32fast::hash(bytes)) {
+        return unicode.value.clone();
+    }
+
+    match fallback {
+        Some(encoding) => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        None => decode_cp437(bytes),
+    }
+}
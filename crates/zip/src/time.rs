@@ -0,0 +1,202 @@
+//! Timestamps of entries.
+//!
+//! The MS-DOS date and time fields have a 2 second precision and no time zone. Normally they're the local
+//! time of whoever made the archive. The NTFS (0x000a) and Info-ZIP extended timestamp (0x5455) extra fields
+//! are more precise and always UTC, so they're preferred when present.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::ExtraField;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Seconds between the NTFS epoch (1601-01-01) and the Unix epoch.
+const NTFS_EPOCH_OFFSET: u64 = 11_644_473_600;
+/// NTFS times are in 100 nanosecond intervals.
+const NTFS_INTERVALS_PER_SECOND: u64 = 10_000_000;
+
+/// An MS-DOS date and time. 1980 to 2107 with a 2 second precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DosDateTime {
+    pub year: u16,
+    /// 1 to 12
+    pub month: u8,
+    /// 1 to 31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    /// Always even.
+    pub second: u8,
+}
+
+impl DosDateTime {
+    /// Split the time and date fields of a header.
+    pub fn from_fields(time: u16, date: u16) -> Self {
+        Self {
+            year: 1980 + (date >> 9),
+            month: ((date >> 5) & 0xF) as u8,
+            day: (date & 0x1F) as u8,
+            hour: (time >> 11) as u8,
+            minute: ((time >> 5) & 0x3F) as u8,
+            second: ((time & 0x1F) * 2) as u8,
+        }
+    }
+
+    /// The time and date fields of a header.
+    pub fn to_fields(&self) -> (u16, u16) {
+        let time =
+            ((self.hour as u16) << 11) | ((self.minute as u16) << 5) | (self.second as u16 / 2);
+        let date =
+            (self.year.saturating_sub(1980) << 9) | ((self.month as u16) << 5) | self.day as u16;
+
+        (time, date)
+    }
+
+    /// Convert a point in time to the time zone `utc_offset` seconds east of UTC. It's clamped to 1980-2107.
+    pub fn from_system_time(time: SystemTime, utc_offset: i32) -> Self {
+        let seconds = unix_seconds(time) + utc_offset as i64;
+
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let seconds = seconds.rem_euclid(SECONDS_PER_DAY);
+
+        let (year, month, day) = civil_from_days(days);
+
+        if year < 1980 {
+            return Self::from_fields(0, (1 << 5) | 1);
+        }
+
+        if year > 2107 {
+            return Self::from_fields((23 << 11) | (59 << 5) | 29, (127 << 9) | (12 << 5) | 31);
+        }
+
+        Self {
+            year: year as u16,
+            month,
+            day,
+            hour: (seconds / 3600) as u8,
+            minute: ((seconds % 3600) / 60) as u8,
+            second: ((seconds % 60) & !1) as u8,
+        }
+    }
+
+    /// The point in time, reading the date and time as being `utc_offset` seconds east of UTC.
+    ///
+    /// Out of range values, like a zeroed date, are clamped rather than rejected.
+    pub fn to_system_time(&self, utc_offset: i32) -> SystemTime {
+        let days = days_from_civil(self.year as i64, self.month.clamp(1, 12), self.day.max(1));
+
+        let seconds = days * SECONDS_PER_DAY
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+            - utc_offset as i64;
+
+        unix_time(seconds)
+    }
+}
+
+/// When an entry was last modified, and whether the time zone is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryTime {
+    /// From the NTFS or extended timestamp extra fields.
+    Utc(SystemTime),
+    /// From the MS-DOS date and time fields, in an unknown time zone.
+    Local(DosDateTime),
+}
+
+impl EntryTime {
+    /// The point in time. Local times are read as being `utc_offset` seconds east of UTC.
+    pub fn to_system_time(&self, utc_offset: i32) -> SystemTime {
+        match self {
+            Self::Utc(v) => *v,
+            Self::Local(v) => v.to_system_time(utc_offset),
+        }
+    }
+
+    pub fn is_utc(&self) -> bool {
+        matches!(self, Self::Utc(_))
+    }
+}
+
+/// The modification and access times from the extra field. NTFS is preferred for its precision.
+pub(crate) fn extra_field_times(
+    extra_field: &[ExtraField],
+) -> (Option<SystemTime>, Option<SystemTime>) {
+    let ntfs = extra_field.iter().find_map(|v| match v {
+        ExtraField::Ntfs(v) => Some(v),
+        _ => None,
+    });
+
+    let extended = extra_field.iter().find_map(|v| match v {
+        ExtraField::ExtendedTimestamp(v) => Some(v),
+        _ => None,
+    });
+
+    let modified = ntfs
+        .and_then(|v| ntfs_time(v.last_modified))
+        .or_else(|| extended?.last_modified.map(|v| unix_time(v.into())));
+
+    let accessed = ntfs
+        .and_then(|v| ntfs_time(v.last_accessed))
+        .or_else(|| extended?.last_accessed.map(|v| unix_time(v.into())));
+
+    (modified, accessed)
+}
+
+/// Seconds since the Unix epoch, which can be before it.
+pub(crate) fn unix_time(seconds: i64) -> SystemTime {
+    if seconds < 0 {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    } else {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    }
+}
+
+/// Whole seconds since the Unix epoch, which can be before it.
+pub(crate) fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(v) => v.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+/// 100 nanosecond intervals since 1601-01-01 UTC. Zero means it isn't set.
+pub(crate) fn ntfs_time(intervals: u64) -> Option<SystemTime> {
+    if intervals == 0 {
+        return None;
+    }
+
+    let seconds = (intervals / NTFS_INTERVALS_PER_SECOND) as i64 - NTFS_EPOCH_OFFSET as i64;
+    let nanos = (intervals % NTFS_INTERVALS_PER_SECOND) * 100;
+
+    Some(unix_time(seconds) + Duration::from_nanos(nanos))
+}
+
+// Conversions between days since the Unix epoch and civil dates. https://howardhinnant.github.io/date_algorithms.html
+
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month as u8, day as u8)
+}
+
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,341 @@
+//! Split and spanned archives.
+//!
+//! A split archive is stored over several files, or volumes. Info-ZIP and WinZip name them "name.z01",
+//! "name.z02", ... with the last one being "name.zip", and the disk numbers in the headers count the volumes.
+//! 7-Zip instead cuts a normal archive into "name.zip.001", "name.zip.002", ... where every disk number is 0.
+//!
+//! Either way the volumes are read as one stream with every `(disk, offset)` pair mapped onto it.
+
+use std::{
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::{
+    fs::{self, File},
+    io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, ReadBuf},
+};
+
+use crate::{Error, Result, SIGNATURE_SIZE};
+
+/// Written at the start of the first volume of a split archive, before the first Local File Header.
+pub(crate) const SPANNING_SIG: [u8; 4] = [0x50, 0x4B, 0x07, 0x08];
+/// Written instead of the spanning signature when an archive only ended up needing one volume.
+pub(crate) const SPANNING_TEMPORARY_SIG: [u8; 4] = [0x50, 0x4B, 0x30, 0x30];
+
+struct Volume {
+    file: File,
+    /// Where the volume starts in the set.
+    start: u64,
+    /// The last volume's size isn't tracked, so the set can be read up to wherever it ends.
+    size: u64,
+}
+
+impl Volume {
+    fn end(&self) -> u64 {
+        self.start + self.size
+    }
+}
+
+/// The file, or files, an archive is read from.
+///
+/// Reading and seeking treat the volumes as one file.
+pub struct ArchiveFile {
+    volumes: Vec<Volume>,
+
+    /// The volume `position` is in.
+    current: usize,
+    /// Position in the set.
+    position: u64,
+    /// The current volume hasn't been moved to `position` yet.
+    needs_seek: bool,
+    /// A seek of the current volume was started and hasn't completed.
+    seeking: bool,
+    /// Seek to this far from the end of the last volume, rather than to `position`.
+    seek_from_end: Option<i64>,
+}
+
+impl ArchiveFile {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(fs::OpenOptions::new().read(true).open(path).await?.into())
+    }
+
+    /// Open every volume of a split archive.
+    ///
+    /// `path` is either the last volume of an Info-ZIP style set ("name.zip"), which finds "name.z01", "name.z02"
+    /// and so on beside it, or the first volume of a 7-Zip style set ("name.zip.001"), which finds the numbers
+    /// after it. Without any other volumes it's opened like a normal archive.
+    pub async fn open_split(path: impl AsRef<Path>) -> Result<Self> {
+        let mut files = Vec::new();
+
+        for path in volume_paths(path.as_ref()).await {
+            files.push(fs::OpenOptions::new().read(true).open(path).await?);
+        }
+
+        Self::from_files(files).await
+    }
+
+    /// Read `files` in order as the volumes of a split archive.
+    pub async fn from_files(files: Vec<File>) -> Result<Self> {
+        let count = files.len();
+
+        let mut volumes = Vec::with_capacity(count);
+        let mut start = 0;
+
+        for (index, file) in files.into_iter().enumerate() {
+            let size = if index + 1 == count {
+                u64::MAX - start
+            } else {
+                file.metadata().await?.len()
+            };
+
+            volumes.push(Volume { file, start, size });
+
+            start += size;
+        }
+
+        if volumes.is_empty() {
+            return Err(Error::MissingVolume(0));
+        }
+
+        Ok(Self {
+            volumes,
+            current: 0,
+            position: 0,
+            needs_seek: true,
+            seeking: false,
+            seek_from_end: None,
+        })
+    }
+
+    pub fn volume_count(&self) -> usize {
+        self.volumes.len()
+    }
+
+    /// Where `offset` on `disk` is in the set.
+    pub(crate) fn disk_position(&self, disk: u32, offset: u64) -> Result<u64> {
+        let volume = self
+            .volumes
+            .get(disk as usize)
+            .ok_or(Error::MissingVolume(disk))?;
+
+        Ok(volume.start + offset)
+    }
+
+    /// Size of the spanning signature at the start of the set. 0 if there isn't one.
+    pub(crate) async fn spanning_marker_size(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Start(0)).await?;
+
+        let mut signature = [0u8; SIGNATURE_SIZE];
+
+        match self.read_exact(&mut signature).await {
+            Ok(_) if signature == SPANNING_SIG || signature == SPANNING_TEMPORARY_SIG => {
+                Ok(SIGNATURE_SIZE as u64)
+            }
+            Ok(_) => Ok(0),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Index of the volume `position` is in. Past the end is the last volume.
+    fn volume_at(&self, position: u64) -> usize {
+        self.volumes
+            .partition_point(|v| v.end() <= position)
+            .min(self.volumes.len() - 1)
+    }
+
+    /// Move the current volume to `position`.
+    fn poll_seek_volume(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.needs_seek {
+            return Poll::Ready(Ok(()));
+        }
+
+        let volume = &mut self.volumes[self.current];
+
+        if !self.seeking {
+            let position = match self.seek_from_end {
+                Some(v) => SeekFrom::End(v),
+                None => SeekFrom::Start(self.position.saturating_sub(volume.start)),
+            };
+
+            Pin::new(&mut volume.file).start_seek(position)?;
+            self.seeking = true;
+        }
+
+        let result = ready!(Pin::new(&mut volume.file).poll_complete(cx));
+
+        self.seeking = false;
+        self.needs_seek = false;
+
+        let offset = result?;
+
+        if self.seek_from_end.take().is_some() {
+            self.position = volume.start + offset;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl From<File> for ArchiveFile {
+    fn from(file: File) -> Self {
+        Self {
+            volumes: vec![Volume {
+                file,
+                start: 0,
+                size: u64::MAX,
+            }],
+            current: 0,
+            position: 0,
+            needs_seek: true,
+            seeking: false,
+            seek_from_end: None,
+        }
+    }
+}
+
+impl AsyncRead for ArchiveFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            ready!(this.poll_seek_volume(cx))?;
+
+            let volume = &mut this.volumes[this.current];
+            let remaining = volume.end().saturating_sub(this.position);
+
+            if remaining == 0 || buf.remaining() == 0 {
+                // Nothing further in the set.
+                if this.current + 1 >= this.volumes.len() || buf.remaining() == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+
+                this.current += 1;
+                this.needs_seek = true;
+
+                continue;
+            }
+
+            let limit = buf
+                .remaining()
+                .min(remaining.min(usize::MAX as u64) as usize);
+            let mut volume_buf = ReadBuf::new(buf.initialize_unfilled_to(limit));
+
+            ready!(Pin::new(&mut volume.file).poll_read(cx, &mut volume_buf))?;
+
+            let amount = volume_buf.filled().len();
+
+            // The volume is shorter than when it was opened. Treat it as the end of the set.
+            if amount == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            buf.advance(amount);
+            this.position += amount as u64;
+
+            return Poll::Ready(Ok(()));
+        }
+    }
+}
+
+impl AsyncSeek for ArchiveFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+
+        if this.seeking {
+            return Err(io::Error::other("other file operation is pending"));
+        }
+
+        let target = match position {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::Current(v) => this.position.checked_add_signed(v),
+            // Only the last volume knows where the set ends.
+            SeekFrom::End(v) => {
+                this.current = this.volumes.len() - 1;
+                this.seek_from_end = Some(v);
+                this.needs_seek = true;
+
+                return Ok(());
+            }
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        if target != this.position || this.needs_seek {
+            this.current = this.volume_at(target);
+            this.position = target;
+            this.needs_seek = true;
+        }
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_seek_volume(cx))?;
+
+        Poll::Ready(Ok(this.position))
+    }
+}
+
+/// The paths of every volume in a set, in order. Only the ones which exist are included.
+async fn volume_paths(path: &Path) -> Vec<PathBuf> {
+    let extension = path
+        .extension()
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut paths = Vec::new();
+
+    // 7-Zip: "name.zip.001", "name.zip.002", ...
+    if let Ok(first) = extension.parse::<u32>() {
+        let width = extension.len();
+
+        paths.push(path.to_path_buf());
+
+        for number in first + 1.. {
+            let next = path.with_extension(format!("{number:0width$}"));
+
+            if !is_file(&next).await {
+                break;
+            }
+
+            paths.push(next);
+        }
+
+        return paths;
+    }
+
+    // Info-ZIP: "name.z01", "name.z02", ... "name.zip". Keep the case of the extension.
+    let prefix = if extension.starts_with('Z') { "Z" } else { "z" };
+
+    for number in 1.. {
+        let volume = path.with_extension(format!("{prefix}{number:02}"));
+
+        if !is_file(&volume).await {
+            break;
+        }
+
+        paths.push(volume);
+    }
+
+    paths.push(path.to_path_buf());
+
+    paths
+}
+
+async fn is_file(path: &Path) -> bool {
+    fs::metadata(path).await.is_ok_and(|v| v.is_file())
+}
@@ -0,0 +1,919 @@
+//! Creates new archives, and changes existing ones.
+//!
+//! Each entry is written as a Local File Header followed by its compressed data. The CRC-32 and sizes aren't known
+//! until the data has been written, so the Local File Header is filled in afterwards. The Central Directory and
+//! End of Central Directory records are written once every entry has been added.
+//!
+//! An existing archive has its new entries written over its Central Directory. Removing or renaming an entry
+//! moves the entries after it along so there are no gaps, without decompressing anything. Entries of other
+//! archives are copied as they're stored in the same way.
+
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    time::SystemTime,
+};
+
+use crc32fast::Hasher;
+use tokio::{
+    fs::{self, File},
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+
+use crate::{
+    bytes_to_u16, compression::CompressionType, remove_extra_fields, time::unix_seconds, Archive,
+    CentralDirHeader, DosDateTime, Error, ExtendedTimestamp, ExtraField, Result, Zip64ExtendedInfo,
+    CENTRAL_DIR_SIG, DOS_DIRECTORY_ATTR, END_CENTRAL_DIR_SIG, GP_FLAG_DEFLATE_OPTION_SHIFT,
+    GP_FLAG_UTF8, LOCAL_FILE_HEADER_SIG, LOCAL_FILE_HEADER_SIZE_KNOWN, UNICODE_PATH_EXTRA_ID,
+    ZIP64_END_CENTRAL_DIR_LOCATOR_SIG, ZIP64_END_CENTRAL_DIR_SIG, ZIP64_END_CENTRAL_DIR_SIZE_KNOWN,
+    ZIP64_EXTRA_ID,
+};
+
+/// Amount of data read from a source at a time.
+const WRITE_CHUNK_SIZE: usize = 32 * 1024;
+/// Amount of compressed data held before it's written to the archive.
+const WRITE_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Offset of the general purpose flag in a Local File Header.
+const LOCAL_GP_FLAG_OFFSET: usize = 6;
+/// Offset of the file name length in a Local File Header. The extra field length follows it.
+const LOCAL_FILE_NAME_LENGTH_OFFSET: usize = 26;
+
+/// Version made by. Upper byte 0 = MS-DOS attributes, lower byte = specification 6.3.
+const VERSION_MADE_BY: u16 = 63;
+/// Version needed to extract a stored file.
+const VERSION_DEFAULT: u16 = 10;
+/// Version needed to extract a folder or a Deflated file.
+const VERSION_DEFLATE: u16 = 20;
+/// Version needed to extract when Zip64 format extensions are used.
+const VERSION_ZIP64: u16 = 45;
+
+/// Entries this large reserve a Zip64 extra field in their Local File Header up front.
+/// Deflate can make incompressible data slightly larger, so there's some room left.
+const ZIP64_RESERVE_THRESHOLD: u64 = u32::MAX as u64 - 0x0100_0000;
+/// Size of the Zip64 extra field in a Local File Header. The header, and both sizes.
+const ZIP64_LOCAL_EXTRA_SIZE: usize = 20;
+/// Extended timestamp flag for the modification time, the only one we write.
+const EXTENDED_TIMESTAMP_MODIFIED: u8 = 1 << 0;
+
+/// How an entry is written.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryOptions {
+    /// Only Store and Deflate can be written.
+    pub compression: CompressionType,
+    /// Deflate compression level from 0 to 9. Defaults to 6.
+    pub level: Option<u32>,
+    /// Last modification time. Defaults to the file's modification time when adding a path, otherwise now.
+    pub last_modified: Option<SystemTime>,
+    /// Seconds east of UTC to write the MS-DOS time in, since it has no time zone. e.g. 3600 for UTC+1
+    ///
+    /// An extended timestamp extra field holding the UTC time is written too, which readers prefer.
+    pub utc_offset: i32,
+}
+
+impl EntryOptions {
+    pub fn stored() -> Self {
+        Self {
+            compression: CompressionType::None,
+            ..Self::default()
+        }
+    }
+
+    pub fn deflated(level: u32) -> Self {
+        Self {
+            level: Some(level),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for EntryOptions {
+    fn default() -> Self {
+        Self {
+            compression: CompressionType::Deflate,
+            level: None,
+            last_modified: None,
+            utc_offset: 0,
+        }
+    }
+}
+
+/// What's needed to write the Central Directory record of an entry in the archive.
+#[derive(Debug, Clone)]
+struct WrittenEntry {
+    by_version: u16,
+    /// Version needed to extract, before Zip64 is taken into account.
+    version_needed: u16,
+    file_name: String,
+    file_name_raw: Vec<u8>,
+    gp_flag: u16,
+    compression: CompressionType,
+    file_last_mod_time: u16,
+    file_last_mod_date: u16,
+    crc_32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    internal_file_attr: u16,
+    external_file_attr: u32,
+    relative_offset: u64,
+    /// The Local File Header has a Zip64 extra field.
+    local_zip64: bool,
+    /// The Central Directory extra field as it's stored, apart from Zip64 which is worked out when it's written.
+    extra_field: Vec<u8>,
+    file_comment: Vec<u8>,
+    /// The Local File Header, data and data descriptor.
+    size: u64,
+}
+
+impl WrittenEntry {
+    /// An entry which is already in an archive. `relative_offset`, `local_zip64` and `size` are from its extent.
+    fn existing(
+        header: &CentralDirHeader,
+        relative_offset: u64,
+        local_zip64: bool,
+        size: u64,
+    ) -> Self {
+        Self {
+            by_version: header.by_version.to_u16(),
+            version_needed: header.min_version.into(),
+            file_name: header.file_name.clone(),
+            file_name_raw: header.file_name_raw.clone(),
+            gp_flag: header.gp_flag,
+            compression: header.compression,
+            file_last_mod_time: header.file_last_mod_time,
+            file_last_mod_date: header.file_last_mod_date,
+            crc_32: header.crc_32,
+            compressed_size: header.compressed_size,
+            uncompressed_size: header.uncompressed_size,
+            internal_file_attr: header.internal_file_attr,
+            external_file_attr: header.external_file_attr,
+            relative_offset,
+            local_zip64,
+            extra_field: remove_extra_fields(&header.extra_field_raw, &[ZIP64_EXTRA_ID]),
+            file_comment: header.file_comment_raw.clone(),
+            size,
+        }
+    }
+
+    fn min_version(&self, zip64: bool) -> u16 {
+        if zip64 {
+            self.version_needed.max(VERSION_ZIP64)
+        } else {
+            self.version_needed
+        }
+    }
+
+    /// The Local File Header. The CRC-32 and sizes are filled in once the data has been written.
+    /// Only for entries we write. The Zip64 extra field goes last so it can be filled in afterwards.
+    fn local_header(&self) -> Result<Vec<u8>> {
+        let mut extra_field = self.extra_field.clone();
+
+        if self.local_zip64 {
+            extra_field.extend_from_slice(&self.local_zip64_extra());
+        }
+
+        let mut bytes = Vec::with_capacity(30 + self.file_name_raw.len() + extra_field.len());
+
+        bytes.extend_from_slice(&LOCAL_FILE_HEADER_SIG);
+        bytes.extend_from_slice(&self.min_version(self.local_zip64).to_le_bytes());
+        bytes.extend_from_slice(&self.gp_flag.to_le_bytes());
+        bytes.extend_from_slice(&u16::from(self.compression).to_le_bytes());
+        bytes.extend_from_slice(&self.file_last_mod_time.to_le_bytes());
+        bytes.extend_from_slice(&self.file_last_mod_date.to_le_bytes());
+        bytes.extend_from_slice(&self.local_sizes());
+        bytes.extend_from_slice(&field_length("file name", &self.file_name_raw)?);
+        bytes.extend_from_slice(&field_length("extra field", &extra_field)?);
+        bytes.extend_from_slice(&self.file_name_raw);
+        bytes.extend_from_slice(&extra_field);
+
+        Ok(bytes)
+    }
+
+    /// The CRC-32 and both sizes of the Local File Header.
+    fn local_sizes(&self) -> [u8; 12] {
+        let mut bytes = [0u8; 12];
+
+        bytes[0..4].copy_from_slice(&self.crc_32.to_le_bytes());
+
+        if self.local_zip64 {
+            bytes[4..12].fill(0xFF);
+        } else {
+            bytes[4..8].copy_from_slice(&(self.compressed_size as u32).to_le_bytes());
+            bytes[8..12].copy_from_slice(&(self.uncompressed_size as u32).to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// The Zip64 extra field of the Local File Header. It MUST include both sizes.
+    fn local_zip64_extra(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ZIP64_LOCAL_EXTRA_SIZE);
+
+        ExtraField::Zip64(Zip64ExtendedInfo {
+            uncompressed_size: Some(self.uncompressed_size),
+            compressed_size: Some(self.compressed_size),
+            ..Zip64ExtendedInfo::default()
+        })
+        .write(&mut bytes);
+
+        bytes
+    }
+
+    /// The Zip64 extra field can push the extra field past what fits, even though it fit when it was read.
+    fn central_header(&self) -> Result<Vec<u8>> {
+        // Only the values which don't fit are placed in the Zip64 extra field.
+        let saturate = |value: u64| (value >= u32::MAX as u64).then_some(value);
+
+        let zip64 = Zip64ExtendedInfo {
+            uncompressed_size: saturate(self.uncompressed_size),
+            compressed_size: saturate(self.compressed_size),
+            relative_offset: saturate(self.relative_offset),
+            disk_start_number: None,
+        };
+
+        let is_zip64 = zip64 != Zip64ExtendedInfo::default();
+
+        let mut extra_field = if is_zip64 {
+            ExtraField::to_bytes(&[ExtraField::Zip64(zip64)])
+        } else {
+            Vec::new()
+        };
+
+        extra_field.extend_from_slice(&self.extra_field);
+
+        let uncompressed_size = zip64
+            .uncompressed_size
+            .map_or(self.uncompressed_size as u32, |_| u32::MAX);
+        let compressed_size = zip64
+            .compressed_size
+            .map_or(self.compressed_size as u32, |_| u32::MAX);
+        let relative_offset = zip64
+            .relative_offset
+            .map_or(self.relative_offset as u32, |_| u32::MAX);
+
+        let extra_size = extra_field.len();
+
+        let mut bytes = Vec::with_capacity(
+            46 + self.file_name_raw.len() + extra_size + self.file_comment.len(),
+        );
+
+        bytes.extend_from_slice(&CENTRAL_DIR_SIG);
+        bytes.extend_from_slice(&self.by_version.to_le_bytes());
+        bytes.extend_from_slice(&self.min_version(self.local_zip64 || is_zip64).to_le_bytes());
+        bytes.extend_from_slice(&self.gp_flag.to_le_bytes());
+        bytes.extend_from_slice(&u16::from(self.compression).to_le_bytes());
+        bytes.extend_from_slice(&self.file_last_mod_time.to_le_bytes());
+        bytes.extend_from_slice(&self.file_last_mod_date.to_le_bytes());
+        bytes.extend_from_slice(&self.crc_32.to_le_bytes());
+        bytes.extend_from_slice(&compressed_size.to_le_bytes());
+        bytes.extend_from_slice(&uncompressed_size.to_le_bytes());
+        bytes.extend_from_slice(&field_length("file name", &self.file_name_raw)?);
+        bytes.extend_from_slice(&field_length("extra field", &extra_field)?);
+        bytes.extend_from_slice(&field_length("file comment", &self.file_comment)?);
+        // Disk number start.
+        bytes.extend_from_slice(&[0; 2]);
+        bytes.extend_from_slice(&self.internal_file_attr.to_le_bytes());
+        bytes.extend_from_slice(&self.external_file_attr.to_le_bytes());
+        bytes.extend_from_slice(&relative_offset.to_le_bytes());
+        bytes.extend_from_slice(&self.file_name_raw);
+
+        bytes.extend_from_slice(&extra_field);
+        bytes.extend_from_slice(&self.file_comment);
+
+        Ok(bytes)
+    }
+}
+
+/// Writes a new archive, or changes an existing one.
+///
+/// Nothing is readable until [`ArchiveWriter::finish`] writes the Central Directory.
+pub struct ArchiveWriter {
+    file: File,
+
+    /// Where the next Local File Header will be written.
+    offset: u64,
+
+    /// In the order they're stored.
+    entries: Vec<WrittenEntry>,
+
+    comment: Vec<u8>,
+}
+
+impl ArchiveWriter {
+    /// Create the archive, replacing the file if it already exists.
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(
+            fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+                .await?,
+        ))
+    }
+
+    /// Write the archive from the start of an empty file.
+    ///
+    /// The file also has to be readable to remove or rename entries.
+    pub fn new(file: File) -> Self {
+        Self {
+            file,
+            offset: 0,
+            entries: Vec::new(),
+            comment: Vec::new(),
+        }
+    }
+
+    /// Open an existing archive to add, remove or rename entries.
+    ///
+    /// The existing entries and comment are kept. Until [`ArchiveWriter::finish`] is called the archive isn't
+    /// readable, since new entries are written over its Central Directory.
+    pub async fn append(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let mut archive = Archive::open(path).await?;
+        let files = archive.list_files().await?;
+
+        let mut entries = Vec::with_capacity(files.len());
+
+        for file in &files {
+            let extent = file.extent(&mut archive).await?;
+
+            entries.push(WrittenEntry::existing(
+                file,
+                extent.offset,
+                extent.local_header.is_zip64,
+                extent.size,
+            ));
+        }
+
+        entries.sort_by_key(|v| v.relative_offset);
+
+        // Anything between the last entry and the Central Directory is kept.
+        let entries_end = entries
+            .last()
+            .map(|v| v.relative_offset + v.size)
+            .unwrap_or_default();
+
+        let offset = archive.end_header.curr_offset.max(entries_end);
+        let comment = archive.info().comment_raw;
+
+        drop(archive);
+
+        Ok(Self {
+            file: fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path)
+                .await?,
+            offset,
+            entries,
+            comment,
+        })
+    }
+
+    /// Set the archive comment which is written in the End of Central Directory record.
+    ///
+    /// The comment has to fit in 65,535 bytes.
+    pub fn set_comment(&mut self, comment: impl Into<String>) -> Result<()> {
+        let comment = comment.into().into_bytes();
+
+        field_length("archive comment", &comment)?;
+        self.comment = comment;
+
+        Ok(())
+    }
+
+    /// The names of the entries, in the order they're stored.
+    pub fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|v| v.file_name.as_str())
+    }
+
+    /// Remove an entry. The entries after it are moved back to fill the space.
+    pub async fn remove(&mut self, name: &str) -> Result<()> {
+        let index = self.position(name)?;
+        let entry = self.entries.remove(index);
+
+        self.compact(index, entry.relative_offset).await
+    }
+
+    /// Rename an entry, rewriting its Local File Header.
+    ///
+    /// A shorter or same length name is written in place and the entries after it are moved back. A longer one
+    /// doesn't fit, so the entry is moved after the others instead.
+    pub async fn rename(&mut self, name: &str, new_name: impl Into<String>) -> Result<()> {
+        let new_name = new_name.into();
+        let name_length = field_length("file name", new_name.as_bytes())?;
+
+        if self.entries.iter().any(|v| v.file_name == new_name) {
+            return Err(Error::EntryExists(new_name));
+        }
+
+        let index = self.position(name)?;
+        let mut entry = self.entries.remove(index);
+
+        let old_offset = entry.relative_offset;
+        let old_header = self.read_local_header(old_offset).await?;
+
+        if !new_name.is_ascii() {
+            entry.gp_flag |= GP_FLAG_UTF8;
+        }
+
+        // The Unicode Path extra field would no longer match the name, in either header.
+        entry.extra_field = remove_extra_fields(&entry.extra_field, &[UNICODE_PATH_EXTRA_ID]);
+
+        // The local name isn't always the same as the one in the Central Directory.
+        let extra_start = LOCAL_FILE_HEADER_SIZE_KNOWN
+            + bytes_to_u16(
+                &old_header[LOCAL_FILE_NAME_LENGTH_OFFSET..LOCAL_FILE_NAME_LENGTH_OFFSET + 2],
+            ) as usize;
+        let extra_field = remove_extra_fields(&old_header[extra_start..], &[UNICODE_PATH_EXTRA_ID]);
+
+        let mut header = old_header[..LOCAL_FILE_HEADER_SIZE_KNOWN].to_vec();
+        header[LOCAL_GP_FLAG_OFFSET..LOCAL_GP_FLAG_OFFSET + 2]
+            .copy_from_slice(&entry.gp_flag.to_le_bytes());
+        header[LOCAL_FILE_NAME_LENGTH_OFFSET..LOCAL_FILE_NAME_LENGTH_OFFSET + 2]
+            .copy_from_slice(&name_length);
+        header[LOCAL_FILE_NAME_LENGTH_OFFSET + 2..LOCAL_FILE_NAME_LENGTH_OFFSET + 4]
+            .copy_from_slice(&field_length("extra field", &extra_field)?);
+        header.extend_from_slice(new_name.as_bytes());
+        header.extend_from_slice(&extra_field);
+
+        let data_size = entry.size - old_header.len() as u64;
+
+        entry.file_name_raw = new_name.clone().into_bytes();
+        entry.file_name = new_name;
+        entry.size = header.len() as u64 + data_size;
+
+        if header.len() <= old_header.len() {
+            self.file.seek(SeekFrom::Start(old_offset)).await?;
+            self.file.write_all(&header).await?;
+
+            let same_size = header.len() == old_header.len();
+            let next_offset = old_offset + entry.size;
+
+            self.entries.insert(index, entry);
+
+            // Nothing moves when the header is the same size.
+            if same_size {
+                return Ok(());
+            }
+
+            self.copy_within(
+                old_offset + old_header.len() as u64,
+                old_offset + header.len() as u64,
+                data_size,
+            )
+            .await?;
+
+            return self.compact(index + 1, next_offset).await;
+        }
+
+        let new_offset = self.offset;
+
+        self.file.seek(SeekFrom::Start(new_offset)).await?;
+        self.file.write_all(&header).await?;
+
+        self.copy_within(
+            old_offset + old_header.len() as u64,
+            new_offset + header.len() as u64,
+            data_size,
+        )
+        .await?;
+
+        entry.relative_offset = new_offset;
+        self.offset = new_offset + entry.size;
+
+        self.entries.push(entry);
+
+        self.compact(index, old_offset).await
+    }
+
+    /// Add a file with the contents of `data`.
+    pub async fn add_bytes(
+        &mut self,
+        name: impl Into<String>,
+        data: impl AsRef<[u8]>,
+        options: EntryOptions,
+    ) -> Result<()> {
+        let mut data = data.as_ref();
+        let size = data.len() as u64;
+
+        self.add_entry(name.into(), &mut data, Some(size), options, 0)
+            .await
+    }
+
+    /// Add a file with the contents of the file at `path`.
+    pub async fn add_path(
+        &mut self,
+        name: impl Into<String>,
+        path: impl AsRef<Path>,
+        mut options: EntryOptions,
+    ) -> Result<()> {
+        let mut file = File::open(path).await?;
+        let metadata = file.metadata().await?;
+
+        if options.last_modified.is_none() {
+            options.last_modified = metadata.modified().ok();
+        }
+
+        self.add_entry(name.into(), &mut file, Some(metadata.len()), options, 0)
+            .await
+    }
+
+    /// Add a file with the contents read from `reader`.
+    ///
+    /// The size isn't known up front so the Local File Header always uses the Zip64 format.
+    pub async fn add_reader<R: AsyncRead + Unpin>(
+        &mut self,
+        name: impl Into<String>,
+        mut reader: R,
+        options: EntryOptions,
+    ) -> Result<()> {
+        self.add_entry(name.into(), &mut reader, None, options, 0)
+            .await
+    }
+
+    /// Add a folder. A trailing slash is added to the name if it's missing.
+    pub async fn add_directory(
+        &mut self,
+        name: impl Into<String>,
+        options: EntryOptions,
+    ) -> Result<()> {
+        let mut name = name.into();
+
+        if !name.ends_with('/') {
+            name.push('/');
+        }
+
+        let options = EntryOptions {
+            compression: CompressionType::None,
+            ..options
+        };
+
+        self.add_entry(
+            name,
+            &mut tokio::io::empty(),
+            Some(0),
+            options,
+            DOS_DIRECTORY_ATTR,
+        )
+        .await
+    }
+
+    async fn add_entry<R: AsyncRead + Unpin>(
+        &mut self,
+        file_name: String,
+        reader: &mut R,
+        size_hint: Option<u64>,
+        options: EntryOptions,
+        external_file_attr: u32,
+    ) -> Result<()> {
+        // Nothing may have been written since the archive was opened, or it was last modified.
+        self.file.seek(SeekFrom::Start(self.offset)).await?;
+
+        let mut encoder = options.compression.encoder(options.level)?;
+
+        let last_modified = options.last_modified.unwrap_or_else(SystemTime::now);
+
+        let (file_last_mod_time, file_last_mod_date) =
+            DosDateTime::from_system_time(last_modified, options.utc_offset).to_fields();
+
+        // Only fits 1901 to 2038. With only the modification time it's the same in both headers.
+        let extra_field = i32::try_from(unix_seconds(last_modified))
+            .ok()
+            .map(|v| {
+                ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+                    flags: EXTENDED_TIMESTAMP_MODIFIED,
+                    last_modified: Some(v),
+                    ..ExtendedTimestamp::default()
+                })
+            })
+            .map(|v| ExtraField::to_bytes(&[v]))
+            .unwrap_or_default();
+
+        let mut gp_flag = 0;
+
+        if options.compression == CompressionType::Deflate {
+            gp_flag |= deflate_option(options.level) << GP_FLAG_DEFLATE_OPTION_SHIFT;
+        }
+
+        if !file_name.is_ascii() {
+            gp_flag |= GP_FLAG_UTF8;
+        }
+
+        let version_needed =
+            if options.compression == CompressionType::Deflate || file_name.ends_with('/') {
+                VERSION_DEFLATE
+            } else {
+                VERSION_DEFAULT
+            };
+
+        let mut entry = WrittenEntry {
+            by_version: VERSION_MADE_BY,
+            version_needed,
+            file_name_raw: file_name.clone().into_bytes(),
+            file_name,
+            gp_flag,
+            compression: options.compression,
+            file_last_mod_time,
+            file_last_mod_date,
+            crc_32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            internal_file_attr: 0,
+            external_file_attr,
+            relative_offset: self.offset,
+            local_zip64: size_hint.is_none_or(|v| v >= ZIP64_RESERVE_THRESHOLD),
+            extra_field,
+            file_comment: Vec::new(),
+            size: 0,
+        };
+
+        // Small entries are written in one go once the Local File Header has been filled in.
+        let mut pending = entry.local_header()?;
+        let header_size = pending.len();
+        let data_offset = entry.relative_offset + header_size as u64;
+        let mut flushed = 0u64;
+
+        let mut hasher = Hasher::new();
+        let mut input = vec![0u8; WRITE_CHUNK_SIZE];
+
+        loop {
+            let amount = reader.read(&mut input).await?;
+
+            if amount == 0 {
+                break;
+            }
+
+            hasher.update(&input[..amount]);
+            entry.uncompressed_size += amount as u64;
+
+            encoder.encode(&input[..amount], &mut pending)?;
+
+            if pending.len() >= WRITE_BUFFER_SIZE {
+                self.file.write_all(&pending).await?;
+                flushed += pending.len() as u64;
+                pending.clear();
+            }
+        }
+
+        encoder.finish(&mut pending)?;
+
+        entry.crc_32 = hasher.finalize();
+        entry.compressed_size = flushed + pending.len() as u64 - header_size as u64;
+
+        // The source grew past what the Local File Header has room for.
+        if !entry.local_zip64
+            && (entry.compressed_size >= u32::MAX as u64
+                || entry.uncompressed_size >= u32::MAX as u64)
+        {
+            return Err(Error::SizeMismatch {
+                expected: size_hint.unwrap_or_default(),
+                found: entry.uncompressed_size,
+            });
+        }
+
+        // Fill in the CRC-32 and sizes now that they're known.
+        if flushed != 0 {
+            self.file.write_all(&pending).await?;
+
+            self.file
+                .seek(SeekFrom::Start(entry.relative_offset + 14))
+                .await?;
+            self.file.write_all(&entry.local_sizes()).await?;
+
+            if entry.local_zip64 {
+                self.file
+                    .seek(SeekFrom::Start(data_offset - ZIP64_LOCAL_EXTRA_SIZE as u64))
+                    .await?;
+                self.file.write_all(&entry.local_zip64_extra()).await?;
+            }
+
+            self.file
+                .seek(SeekFrom::Start(data_offset + entry.compressed_size))
+                .await?;
+        } else {
+            pending[14..26].copy_from_slice(&entry.local_sizes());
+
+            if entry.local_zip64 {
+                pending[header_size - ZIP64_LOCAL_EXTRA_SIZE..header_size]
+                    .copy_from_slice(&entry.local_zip64_extra());
+            }
+
+            self.file.write_all(&pending).await?;
+        }
+
+        self.offset = data_offset + entry.compressed_size;
+
+        entry.size = self.offset - entry.relative_offset;
+
+        self.entries.push(entry);
+
+        Ok(())
+    }
+
+    /// Copy an entry of another archive as it's stored, without decompressing it.
+    ///
+    /// The Local File Header, data and data descriptor are copied unchanged, and the Central Directory record keeps
+    /// its attributes, extra fields and comment. Encrypted entries are copied without needing the password.
+    pub async fn copy_entry(
+        &mut self,
+        archive: &mut Archive,
+        entry: &CentralDirHeader,
+    ) -> Result<()> {
+        if self.entries.iter().any(|v| v.file_name == entry.file_name) {
+            return Err(Error::EntryExists(entry.file_name.clone()));
+        }
+
+        let extent = entry.extent(archive).await?;
+
+        archive.file.seek(SeekFrom::Start(extent.offset)).await?;
+        self.file.seek(SeekFrom::Start(self.offset)).await?;
+
+        let mut buffer = vec![0u8; WRITE_BUFFER_SIZE];
+        let mut copied = 0;
+
+        while copied < extent.size {
+            let amount = (extent.size - copied).min(buffer.len() as u64) as usize;
+
+            // The source can be split, and reads stop at the end of each volume.
+            let read = archive.file.read(&mut buffer[..amount]).await?;
+
+            if read == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+
+            self.file.write_all(&buffer[..read]).await?;
+
+            copied += read as u64;
+        }
+
+        self.entries.push(WrittenEntry::existing(
+            entry,
+            self.offset,
+            extent.local_header.is_zip64,
+            extent.size,
+        ));
+
+        self.offset += extent.size;
+
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        self.entries
+            .iter()
+            .position(|v| v.file_name == name)
+            .ok_or_else(|| Error::EntryNotFound(name.to_string()))
+    }
+
+    /// Move the entries from `index` onwards so they start at `offset` without any gaps.
+    async fn compact(&mut self, index: usize, mut offset: u64) -> Result<()> {
+        for index in index..self.entries.len() {
+            let WrittenEntry {
+                relative_offset,
+                size,
+                ..
+            } = self.entries[index];
+
+            if relative_offset != offset {
+                self.copy_within(relative_offset, offset, size).await?;
+            }
+
+            self.entries[index].relative_offset = offset;
+            offset += size;
+        }
+
+        self.offset = offset;
+
+        Ok(())
+    }
+
+    /// Copy `size` bytes from `from` to `to`. The copy can overlap the original as long as it's earlier in the file.
+    async fn copy_within(&mut self, from: u64, to: u64, size: u64) -> Result<()> {
+        let mut buffer = vec![0u8; WRITE_BUFFER_SIZE];
+        let mut copied = 0;
+
+        while copied < size {
+            let amount = (size - copied).min(buffer.len() as u64) as usize;
+
+            self.file.seek(SeekFrom::Start(from + copied)).await?;
+            self.file.read_exact(&mut buffer[..amount]).await?;
+
+            self.file.seek(SeekFrom::Start(to + copied)).await?;
+            self.file.write_all(&buffer[..amount]).await?;
+
+            copied += amount as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Read the Local File Header at `offset` as it's stored.
+    async fn read_local_header(&mut self, offset: u64) -> Result<Vec<u8>> {
+        let mut header = vec![0u8; LOCAL_FILE_HEADER_SIZE_KNOWN];
+
+        self.file.seek(SeekFrom::Start(offset)).await?;
+        self.file.read_exact(&mut header).await?;
+
+        if header[..4] != LOCAL_FILE_HEADER_SIG {
+            return Err(Error::MissingLocalHeader);
+        }
+
+        let variable_size =
+            bytes_to_u16(&header[26..28]) as usize + bytes_to_u16(&header[28..30]) as usize;
+
+        header.resize(LOCAL_FILE_HEADER_SIZE_KNOWN + variable_size, 0);
+        self.file
+            .read_exact(&mut header[LOCAL_FILE_HEADER_SIZE_KNOWN..])
+            .await?;
+
+        Ok(header)
+    }
+
+    /// Write the Central Directory and End of Central Directory records.
+    ///
+    /// The Zip64 records are used if there are too many entries or the archive is too large.
+    pub async fn finish(mut self) -> Result<File> {
+        let central_dir_offset = self.offset;
+
+        let mut bytes = Vec::new();
+
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.central_header()?);
+        }
+
+        let central_dir_size = bytes.len() as u64;
+        let record_count = self.entries.len() as u64;
+
+        let is_zip64 = record_count >= u16::MAX as u64
+            || central_dir_size >= u32::MAX as u64
+            || central_dir_offset >= u32::MAX as u64;
+
+        if is_zip64 {
+            let zip64_offset = central_dir_offset + central_dir_size;
+
+            bytes.extend_from_slice(&ZIP64_END_CENTRAL_DIR_SIG);
+            // Size of the record, excluding the signature and this field.
+            bytes.extend_from_slice(&(ZIP64_END_CENTRAL_DIR_SIZE_KNOWN as u64 - 12).to_le_bytes());
+            bytes.extend_from_slice(&VERSION_MADE_BY.to_le_bytes());
+            bytes.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            // Number of this disk and the disk with the Central Directory.
+            bytes.extend_from_slice(&[0; 8]);
+            bytes.extend_from_slice(&record_count.to_le_bytes());
+            bytes.extend_from_slice(&record_count.to_le_bytes());
+            bytes.extend_from_slice(&central_dir_size.to_le_bytes());
+            bytes.extend_from_slice(&central_dir_offset.to_le_bytes());
+
+            bytes.extend_from_slice(&ZIP64_END_CENTRAL_DIR_LOCATOR_SIG);
+            bytes.extend_from_slice(&0u32.to_le_bytes());
+            bytes.extend_from_slice(&zip64_offset.to_le_bytes());
+            bytes.extend_from_slice(&1u32.to_le_bytes());
+        }
+
+        let record_count = record_count.min(u16::MAX as u64) as u16;
+
+        bytes.extend_from_slice(&END_CENTRAL_DIR_SIG);
+        // Number of this disk and the disk with the Central Directory.
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&record_count.to_le_bytes());
+        bytes.extend_from_slice(&record_count.to_le_bytes());
+        bytes.extend_from_slice(&(central_dir_size.min(u32::MAX as u64) as u32).to_le_bytes());
+        bytes.extend_from_slice(&(central_dir_offset.min(u32::MAX as u64) as u32).to_le_bytes());
+        bytes.extend_from_slice(&field_length("archive comment", &self.comment)?);
+        bytes.extend_from_slice(&self.comment);
+
+        self.file.seek(SeekFrom::Start(central_dir_offset)).await?;
+        self.file.write_all(&bytes).await?;
+        self.file.flush().await?;
+
+        // What was left of a changed archive.
+        self.file
+            .set_len(central_dir_offset + bytes.len() as u64)
+            .await?;
+
+        Ok(self.file)
+    }
+}
+
+/// The 2 byte length of a file name, extra field or comment, if it fits.
+fn field_length(field: &'static str, value: &[u8]) -> Result<[u8; 2]> {
+    u16::try_from(value.len())
+        .map(u16::to_le_bytes)
+        .map_err(|_| Error::HeaderFieldTooLong {
+            field,
+            found: value.len(),
+        })
+}
+
+/// The general purpose flag Deflate option for a compression level.
+fn deflate_option(level: Option<u32>) -> u16 {
+    match level {
+        Some(8..) => 1,
+        Some(2) => 2,
+        Some(0 | 1) => 3,
+        _ => 0,
+    }
+}
@@ -1,7 +1,7 @@
 use tracing::debug;
 use tracing::{subscriber::set_global_default, Level};
 use tracing_subscriber::FmtSubscriber;
-use zip_archiver::{Archive, Result};
+use zip_archiver::{Archive, EntryKind, Result};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -20,7 +20,7 @@ async fn main() -> Result<()> {
 
     for file in files {
         debug!("{}", file.file_name);
-        debug!("   Is Folder: {}", file.min_version.is_folder());
+        debug!("   Kind: {:?}", file.kind());
         debug!("   GP FLAG: {:#X}", file.gp_flag);
         debug!("   compression: {:?}", file.compression);
         debug!("   min_version: {}", file.min_version);
@@ -28,11 +28,11 @@ async fn main() -> Result<()> {
         debug!("   comp_size: {}", file.compressed_size);
         debug!("   uncomp_size: {}", file.uncompressed_size);
 
-        // if file.min_version.is_file() {
-        //     let contents = file.read(&mut archive).await?;
+        if file.kind() == EntryKind::File {
+            let contents = file.read(&mut archive).await?;
 
-        //     debug!("{contents}");
-        // }
+            debug!("{}", String::from_utf8_lossy(&contents));
+        }
     }
 
     debug!("{:#?}", archive.info());